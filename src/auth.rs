@@ -0,0 +1,338 @@
+use super::*;
+use md5::{Digest, Md5};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::sync::Mutex;
+use zeroize::Zeroize;
+
+// the spec asks for a salt of at least six characters
+pub const MIN_SALT_LEN: usize = 6;
+pub const DEFAULT_SALT_LEN: usize = 16;
+
+// the query params that carry something that logs someone in
+pub(crate) const SECRET_PARAMS: [&str; 4] = ["p", "t", "s", "apiKey"];
+const REDACTED: &str = "***";
+
+// a password, token or key
+// it's wiped from memory when dropped and never shows up in Debug output
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Secret(secret)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Secret(secret.to_string())
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+// how requests prove who they're from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Auth {
+    // sent as a salted token on 1.13.0 and up, and as a hex encoded password before that
+    Password(Secret),
+    // a token and salt worked out ahead of time, see token()
+    // the password itself never has to be kept around, but the server has to be 1.13.0 or newer
+    // (requests to older ones are Error::APIVersionMismatch)
+    Token { token: Secret, salt: String },
+    // OpenSubsonic apiKeyAuthentication, the key stands in for the username as well
+    ApiKey(Secret),
+}
+
+// masks the login params of a url, ex: for a url that ends up in a error
+pub(crate) fn redact_url(url: &mut Url) {
+    if !url.query_pairs().any(|(k, _)| SECRET_PARAMS.contains(&&*k)) {
+        return;
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| {
+            let v = if SECRET_PARAMS.contains(&&*k) {
+                REDACTED.to_string()
+            } else {
+                v.into_owned()
+            };
+            (k.into_owned(), v)
+        })
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+}
+
+// what can come before a param in a bit of text: a plain ? or &,
+// or a & escaped the way json (\u0026) or xml (&amp;) bodies write it
+const PARAM_STARTS: [&str; 4] = ["\\u0026", "&amp;", "?", "&"];
+
+// the same, for urls somewhere in a bit of text (ex: a body that couldn't be decoded)
+pub(crate) fn redact_text(text: &str) -> String {
+    // where a value in a url stops, ex: at the next param or the end of a attribute
+    let value_end =
+        |x: char| matches!(x, '&' | '#' | '"' | '\'' | '<' | '\\') || x.is_whitespace();
+    let param_start = |rest: &str| {
+        rest.char_indices().find_map(|(at, _)| {
+            PARAM_STARTS
+                .iter()
+                .find(|x| rest[at..].starts_with(**x))
+                .map(|x| at + x.len())
+        })
+    };
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = param_start(rest) {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let secret = SECRET_PARAMS.iter().find(|name| {
+            rest.strip_prefix(**name)
+                .is_some_and(|x| x.starts_with('='))
+        });
+        if let Some(name) = secret {
+            let value_len = rest[name.len() + 1..]
+                .find(value_end)
+                .unwrap_or(rest.len() - name.len() - 1);
+            out.push_str(name);
+            out.push('=');
+            out.push_str(REDACTED);
+            rest = &rest[name.len() + 1 + value_len..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// md5(password + salt) as 32 lowercase hex characters
+// ex: token("sesame", "c19b2d") == "26719a1196d2a940705a59634eb18eab"
+pub fn token(password: &str, salt: &str) -> String {
+    let mut md5 = Md5::new();
+    md5.update(password.as_bytes());
+    md5.update(salt.as_bytes());
+    hex(&md5.finalize())
+}
+
+// two lowercase characters per byte, zero padded
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |accum, x| {
+            accum + &format!("{:02x}", x)
+        })
+}
+
+// where the salts come from, a new one is made for every request
+// the os rng unless something else is given to the builder (ex: a seeded one for tests)
+pub(crate) struct Salter {
+    rng: Mutex<Box<dyn RngCore + Send>>,
+    len: usize,
+}
+
+impl Salter {
+    pub(crate) fn new(rng: Box<dyn RngCore + Send>, len: usize) -> Result<Self> {
+        if len < MIN_SALT_LEN {
+            return Err(Error::InvalidArgument(format!(
+                "a salt has to be at least {} characters, not {}",
+                MIN_SALT_LEN, len
+            )));
+        }
+        Ok(Salter {
+            rng: Mutex::new(rng),
+            len,
+        })
+    }
+
+    // len hex characters of randomness
+    fn salt(&self) -> String {
+        let mut bytes = vec![0; self.len.div_ceil(2)];
+        self.rng
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .fill_bytes(&mut bytes);
+        let mut salt = hex(&bytes);
+        salt.truncate(self.len);
+        salt
+    }
+
+    // a fresh (token, salt) pair
+    fn token(&self, password: &str) -> (String, String) {
+        let salt = self.salt();
+        (token(password, &salt), salt)
+    }
+}
+
+impl Default for Salter {
+    fn default() -> Self {
+        Salter {
+            rng: Mutex::new(Box::new(OsRng)),
+            len: DEFAULT_SALT_LEN,
+        }
+    }
+}
+
+impl std::fmt::Debug for Salter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Salter")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SubsonicClient {
+    // a token can't be turned back into the password, so a server before 1.13.0 has no way to check it
+    pub(crate) fn check_auth(&self) -> Result<()> {
+        let since = ApiVersion::new(1, 13, 0);
+        if matches!(self.auth, Auth::Token { .. }) && self.version < since {
+            return Err(Error::APIVersionMismatch(self.version, since));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn auth_args(&self) -> Vec<(&'static str, String)> {
+        let user = ("u", self.username.clone());
+        match &self.auth {
+            Auth::Password(password) if server_req!(self, 1, 13) && !self.token_rejected => {
+                // salted password
+                let (token, salt) = self.salter.token(password.expose());
+                vec![user, ("t", token), ("s", salt)]
+            }
+            Auth::Password(password) => {
+                // hex encoded password, byte by byte so non ascii passwords make it through
+                vec![user, ("p", format!("enc:{}", hex(password.expose().as_bytes())))]
+            }
+            Auth::Token { token, salt } => vec![
+                user,
+                ("t", token.expose().to_string()),
+                ("s", salt.clone()),
+            ],
+            // sending a username along with a key is a error (43)
+            Auth::ApiKey(key) => vec![("apiKey", key.expose().to_string())],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn spec_example() {
+        assert_eq!(token("sesame", "c19b2d"), "26719a1196d2a940705a59634eb18eab");
+
+        // the bytes c1 9b 2d, so the salt from the spec
+        let salter = Salter::new(Box::new(StepRng::new(0x2d9bc1, 0)), 6).unwrap();
+        assert_eq!(
+            salter.token("sesame"),
+            (
+                "26719a1196d2a940705a59634eb18eab".to_string(),
+                "c19b2d".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn tokens_are_always_32_characters() {
+        // md5("sesame7") starts with a byte under 0x10
+        assert_eq!(token("sesame", "7"), "0f310fb5f9729a97ae4949ce4a141134");
+        let salter = Salter::new(Box::new(StdRng::seed_from_u64(7)), 9).unwrap();
+        for _ in 0..500 {
+            let (token, salt) = salter.token("sesame");
+            assert_eq!(token.len(), 32);
+            assert_eq!(salt.len(), 9);
+            assert!(token
+                .chars()
+                .chain(salt.chars())
+                .all(|x| matches!(x, '0'..='9' | 'a'..='f')));
+        }
+    }
+
+    #[test]
+    fn seeded_client() {
+        let client = SubsonicClient::builder("http://localhost:4040/", "admin", "sesame")
+            .salt_rng(StepRng::new(0x2d9bc1, 0))
+            .salt_len(6)
+            .build()
+            .unwrap();
+        let url = client.make_url("ping", &[]).unwrap();
+        let query = |name: &str| url.query_pairs().find(|(k, _)| k == name).unwrap().1;
+        assert_eq!(query("t"), "26719a1196d2a940705a59634eb18eab");
+        assert_eq!(query("s"), "c19b2d");
+
+        assert!(SubsonicClient::builder("http://localhost:4040/", "admin", "sesame")
+            .salt_len(4)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn secrets_stay_out_of_debug() {
+        let client = SubsonicClient::builder("http://localhost:4040/", "admin", "open sesame")
+            .build()
+            .unwrap();
+        let debug = format!("{:?}", client);
+        assert!(debug.contains("admin"));
+        assert!(!debug.contains("open sesame"));
+
+        let auth = Auth::ApiKey("key123".into());
+        assert_eq!(format!("{:?}", auth), "ApiKey(***)");
+    }
+
+    #[test]
+    fn redacting() {
+        let mut url = Url::parse(
+            "http://localhost:4040/rest/ping?u=admin&t=26719a&s=c19b2d&v=1.16.1&apiKey=k&id=t",
+        )
+        .unwrap();
+        redact_url(&mut url);
+        assert_eq!(
+            url.query(),
+            Some("u=admin&t=***&s=***&v=1.16.1&apiKey=***&id=t")
+        );
+
+        assert_eq!(
+            redact_text(r#"<a href="/rest/stream?id=1&p=enc:736573&s=x">, ?t=abc def"#),
+            r#"<a href="/rest/stream?id=1&p=***&s=***">, ?t=*** def"#
+        );
+        assert_eq!(redact_text("a=b&t="), "a=b&t=***");
+        assert_eq!(redact_text("nothing & here"), "nothing & here");
+        assert_eq!(redact_text("?tt=1&u=admin"), "?tt=1&u=admin");
+
+        // json bodies escape the & as \u0026, and xml as &amp;
+        assert_eq!(
+            redact_text(r#"{"url":"/rest/ping?u=admin\u0026t=abc\u0026s=xyz\u0026apiKey=k"}"#),
+            r#"{"url":"/rest/ping?u=admin\u0026t=***\u0026s=***\u0026apiKey=***"}"#
+        );
+        assert_eq!(
+            redact_text(r#"<a href="/rest/ping?u=admin&amp;p=sesame&amp;c=app"/>"#),
+            r#"<a href="/rest/ping?u=admin&amp;p=***&amp;c=app"/>"#
+        );
+    }
+
+    #[test]
+    fn salts() {
+        let salter = Salter::default();
+        assert_eq!(salter.salt().len(), DEFAULT_SALT_LEN);
+        assert_ne!(salter.salt(), salter.salt());
+        assert!(Salter::new(Box::new(OsRng), MIN_SALT_LEN - 1).is_err());
+    }
+}
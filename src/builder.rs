@@ -0,0 +1,550 @@
+use super::*;
+use deserialize::SubsonicErrorCode;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashMap;
+
+// the newest api version this crate knows about
+// used as the version of a client that was built without asking the server
+pub const LATEST_API_VERSION: ApiVersion = ApiVersion::new(1, 16, 1);
+
+// the c param when the builder isn't given one
+pub const DEFAULT_CLIENT_NAME: &str = env!("CARGO_PKG_NAME");
+
+// sets up a SubsonicClient
+// build() makes one without touching the network (the version has to be known ahead of time),
+// connect() asks the server for its version and checks the login first
+pub struct SubsonicClientBuilder {
+    url: String,
+    username: String,
+    auth: Auth,
+    version: ApiVersion,
+    format: ResponseFormat,
+    extensions: HashMap<Extension, Vec<u32>>,
+    client_name: String,
+    user_agent: Option<String>,
+    salt_len: usize,
+    salt_rng: Option<Box<dyn RngCore + Send>>,
+    form_post: bool,
+    client: Option<Client>,
+}
+
+impl SubsonicClientBuilder {
+    pub fn new(
+        url: impl Into<String>,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        SubsonicClientBuilder {
+            url: url.into(),
+            username: username.into(),
+            auth: Auth::Password(Secret::from(password.into())),
+            version: LATEST_API_VERSION,
+            format: ResponseFormat::Xml,
+            extensions: HashMap::new(),
+            client_name: DEFAULT_CLIENT_NAME.to_string(),
+            user_agent: None,
+            salt_len: auth::DEFAULT_SALT_LEN,
+            salt_rng: None,
+            form_post: false,
+            client: None,
+        }
+    }
+
+    // log in some other way than with the password, for Auth::ApiKey the username is ignored
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    // the server version to assume, connect() replaces this with what the server says
+    pub fn version(mut self, version: ApiVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    // xml by default, json is quicker to decode and has more in it on some servers
+    pub fn format(mut self, format: ResponseFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    // a OpenSubsonic extension to assume the server has, connect() replaces these with what the server says
+    pub fn extension(mut self, extension: Extension, versions: &[u32]) -> Self {
+        self.extensions.insert(extension, versions.to_vec());
+        self
+    }
+
+    // the name the server shows for this app (the c param), ex: on the players page
+    pub fn client_name(mut self, name: impl Into<String>) -> Self {
+        self.client_name = name.into();
+        self
+    }
+
+    // sent as the User-Agent header on every request, reqwest's default is used otherwise
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    // how many hex characters go in a salt, at least auth::MIN_SALT_LEN
+    pub fn salt_len(mut self, len: usize) -> Self {
+        self.salt_len = len;
+        self
+    }
+
+    // where salts come from instead of the os, ex: a seeded rng to get the same tokens every run
+    pub fn salt_rng(mut self, rng: impl RngCore + Send + 'static) -> Self {
+        self.salt_rng = Some(Box::new(rng));
+        self
+    }
+
+    // send params as a form POST instead of in the url, once the server advertises formPost
+    // servers without it keep getting GETs
+    pub fn form_post(mut self, form_post: bool) -> Self {
+        self.form_post = form_post;
+        self
+    }
+
+    // use a already set up reqwest client (proxies, timeouts, certificates, etc)
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    // no network calls, endpoints are gated on the version given to the builder
+    pub fn build(self) -> Result<SubsonicClient> {
+        Url::parse(&self.url)
+            .map_err(|e| Error::InvalidArgument(format!("bad server url {:?}: {}", self.url, e)))?;
+        if self.client_name.is_empty() {
+            return Err(Error::InvalidArgument(
+                "the client name can't be empty".to_string(),
+            ));
+        }
+        let client = match self.client {
+            Some(x) => x,
+            None => ClientBuilder::new().build().map_err(|e| {
+                Error::InvalidArgument(format!("could not set up the http client: {}", e))
+            })?,
+        };
+
+        Ok(SubsonicClient {
+            username: self.username,
+            auth: self.auth,
+            token_rejected: false,
+            salter: Salter::new(
+                self.salt_rng.unwrap_or_else(|| Box::new(OsRng)),
+                self.salt_len,
+            )?,
+            client_name: self.client_name,
+            user_agent: self.user_agent,
+            form_post: self.form_post,
+            url: self.url,
+            client,
+            version: self.version,
+            format: self.format,
+            server: ServerInfo {
+                open_subsonic: !self.extensions.is_empty(),
+                extensions: self.extensions,
+                ..ServerInfo::default()
+            },
+        })
+    }
+
+    // finds out the server version, then pings with the login to make sure it works
+    // OpenSubsonic servers are then asked what extensions they have
+    pub async fn connect(self) -> Result<SubsonicClient> {
+        let mut client = self.build()?;
+        let (version, server) = client.probe().await?;
+        client.version = version;
+        client.server = server;
+        match client.ping().await {
+            // some servers can't check tokens (ex: passwords kept in ldap), so try the password itself
+            Err(Error::ServerError(SubsonicErrorCode::TokenAuthNotSupported, _))
+                if matches!(client.auth, Auth::Password(_)) =>
+            {
+                client.token_rejected = true;
+                client.ping().await?;
+            }
+            x => x?,
+        }
+        if client.server.open_subsonic {
+            client.server.extensions = client
+                .get_open_subsonic_extensions()
+                .await?
+                .into_iter()
+                .map(|x| (Extension::from(x.name.as_str()), x.versions))
+                .collect();
+        }
+        Ok(client)
+    }
+}
+
+impl std::fmt::Debug for SubsonicClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubsonicClientBuilder")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("auth", &self.auth)
+            .field("version", &self.version)
+            .field("format", &self.format)
+            .field("extensions", &self.extensions)
+            .field("client_name", &self.client_name)
+            .field("user_agent", &self.user_agent)
+            .field("salt_len", &self.salt_len)
+            .field("form_post", &self.form_post)
+            .field("client", &self.client)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SubsonicClient {
+    pub fn builder(
+        url: impl Into<String>,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> SubsonicClientBuilder {
+        SubsonicClientBuilder::new(url, username, password)
+    }
+
+    // same argument order as builder
+    pub async fn connect(
+        url: impl Into<String>,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<Self> {
+        SubsonicClientBuilder::new(url, username, password)
+            .connect()
+            .await
+    }
+
+    // the version is needed before the login can be sent the right way (token vs password),
+    // so ask without one, every response carries the server version even when it fails
+    async fn probe(&self) -> Result<(ApiVersion, ServerInfo)> {
+        let mut url = Url::parse(&format!("{}/rest/ping", self.url.trim_end_matches('/')))
+            .map_err(|e| Error::InvalidArgument(format!("bad server url {:?}: {}", self.url, e)))?;
+        url.query_pairs_mut().append_pair("c", &self.client_name);
+        let body = async { self.request(url).send().await?.error_for_status()?.text().await }
+            .await
+            .map_err(|e| Error::http("ping", e))?;
+        // no format is sent, so the answer is xml
+        let raw = ResponseFormat::Xml.decode("ping", &body)?;
+        let version = raw
+            .version
+            .parse::<ApiVersion>()
+            .map_err(|e| Error::decode("ping", e, &body))?;
+        Ok((
+            version,
+            ServerInfo {
+                kind: raw.server_type,
+                version: raw.server_version,
+                open_subsonic: raw.open_subsonic,
+                extensions: HashMap::new(),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::{xml, MockServer};
+
+    #[tokio::test]
+    async fn connect_reads_a_two_part_version() {
+        let server = MockServer::start(|req| {
+            if req.has_query("u") {
+                xml("ok", "1.16", "")
+            } else {
+                xml("failed", "1.16", r#"<error code="10"/>"#)
+            }
+        });
+
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+        assert_eq!(client.api_version(), ApiVersion::new(1, 16, 0));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        // the second ping carries the login, as a token since the server is new enough
+        assert_eq!(requests[1].query("u").as_deref(), Some("admin"));
+        assert_eq!(requests[1].query("v").as_deref(), Some("1.16.0"));
+        assert!(requests[1].has_query("t"));
+        assert!(requests[1].has_query("s"));
+        assert!(!requests[1].has_query("p"));
+    }
+
+    #[tokio::test]
+    async fn tokens_need_1_13() {
+        let server = MockServer::start(|_| xml("ok", "1.12.0", ""));
+        let token = auth::token("sesame", "c19b2d");
+
+        let err = SubsonicClient::builder(&server.url, "admin", "")
+            .auth(Auth::Token {
+                token: token.as_str().into(),
+                salt: "c19b2d".to_string(),
+            })
+            .connect()
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::APIVersionMismatch(server, since)
+                if server == ApiVersion::new(1, 12, 0) && since == ApiVersion::new(1, 13, 0)),
+            "{:?}",
+            err
+        );
+        // only the probe went out, the token never did
+        assert_eq!(server.requests().len(), 1);
+        assert!(!server.requests()[0].has_query("t"));
+
+        // a password is sent hex encoded instead
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+        let url = client.make_url("ping", &[]).unwrap();
+        assert!(url.query_pairs().any(|(k, v)| k == "p" && v == "enc:736573616d65"));
+    }
+
+    #[tokio::test]
+    async fn json_responses() {
+        let server = MockServer::start(|req| {
+            let payload = if req.target.starts_with("/rest/getGenres") {
+                r#", "genres": {"genre": [{"songCount": 2, "albumCount": 1, "value": "Rock"}]}"#
+            } else {
+                ""
+            };
+            if req.query("f").as_deref() == Some("json") {
+                (
+                    "application/json",
+                    format!(
+                        r#"{{"subsonic-response": {{"status": "ok", "version": "1.16.1"{}}}}}"#,
+                        payload
+                    ),
+                )
+            } else {
+                // the version probe doesn't ask for a format
+                xml("ok", "1.16.1", "")
+            }
+        });
+
+        let client = SubsonicClient::builder(&server.url, "admin", "sesame")
+            .format(ResponseFormat::Json)
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(client.response_format(), ResponseFormat::Json);
+        let genres = client.get_genres().await.unwrap();
+        assert_eq!(genres.len(), 1);
+        assert_eq!(genres[0].name, "Rock");
+    }
+
+    #[tokio::test]
+    async fn connect_finds_extensions() {
+        let server = MockServer::start(|req| {
+            let body = if req.target.starts_with("/rest/getOpenSubsonicExtensions") {
+                r#"<openSubsonicExtensions name="songLyrics"><versions>1</versions></openSubsonicExtensions>
+                   <openSubsonicExtensions name="somethingNew"><versions>3</versions></openSubsonicExtensions>"#
+            } else if req.target.starts_with("/rest/getLyricsBySongId") {
+                r#"<lyricsList><structuredLyrics lang="eng" synced="false"><line>hey</line></structuredLyrics></lyricsList>"#
+            } else {
+                ""
+            };
+            (
+                "text/xml",
+                format!(
+                    r#"<subsonic-response status="ok" version="1.16.1" type="navidrome" serverVersion="0.53.3" openSubsonic="true">{}</subsonic-response>"#,
+                    body
+                ),
+            )
+        });
+
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+        let info = client.server_info();
+        assert!(info.open_subsonic);
+        assert_eq!(info.kind.as_deref(), Some("navidrome"));
+        assert_eq!(info.version.as_deref(), Some("0.53.3"));
+        assert!(client.supports(&Extension::SongLyrics));
+        assert!(client.supports(&Extension::Other("somethingNew".to_string())));
+        assert_eq!(info.extension_versions(&Extension::SongLyrics), Some(&[1][..]));
+        assert!(!client.supports(&Extension::FormPost));
+
+        let lyrics = client.get_lyrics_by_song_id(&"1".into()).await.unwrap();
+        assert_eq!(lyrics[0].lines.as_ref().unwrap()[0].value, "hey");
+    }
+
+    #[tokio::test]
+    async fn extensions_are_gated() {
+        let server = MockServer::start(|_| xml("ok", "1.16.1", ""));
+
+        // not a OpenSubsonic server, so nothing is advertised and the extension list isn't asked for
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+        assert!(!client.server_info().open_subsonic);
+        assert_eq!(server.requests().len(), 2);
+        match client.get_lyrics_by_song_id(&"1".into()).await {
+            Err(Error::ExtensionNotSupported(Extension::SongLyrics)) => {}
+            x => panic!("expected a missing extension, got {:?}", x),
+        }
+        // nothing was sent
+        assert_eq!(server.requests().len(), 2);
+
+        let offline = SubsonicClient::builder(&server.url, "admin", "sesame")
+            .extension(Extension::SongLyrics, &[1])
+            .build()
+            .unwrap();
+        assert!(offline.supports(&Extension::SongLyrics));
+    }
+
+    #[tokio::test]
+    async fn token_rejection_falls_back_on_the_password() {
+        let server = MockServer::start(|req| {
+            if req.has_query("t") {
+                xml(
+                    "failed",
+                    "1.16.1",
+                    r#"<error code="41" message="Token authentication not supported for LDAP users."/>"#,
+                )
+            } else {
+                xml("ok", "1.16.1", "")
+            }
+        });
+
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+        client.ping().await.unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[3].query("p").as_deref(), Some("enc:736573616d65"));
+    }
+
+    #[tokio::test]
+    async fn rejected_api_keys() {
+        let server = MockServer::start(|req| match req.query("apiKey").as_deref() {
+            Some("good") => xml("ok", "1.16.1", ""),
+            Some(_) => xml("failed", "1.16.1", r#"<error code="44" message="Invalid API key"/>"#),
+            None => xml("failed", "1.16.1", r#"<error code="10"/>"#),
+        });
+
+        let connect = |key: &str| {
+            SubsonicClient::builder(&server.url, "", "")
+                .auth(Auth::ApiKey(key.into()))
+                .connect()
+        };
+        connect("good").await.unwrap();
+        match connect("bad").await {
+            Err(Error::ServerError(SubsonicErrorCode::InvalidApiKey, _)) => {}
+            x => panic!("expected a invalid key, got {:?}", x),
+        }
+    }
+
+    #[tokio::test]
+    async fn client_name_is_always_sent() {
+        let server = MockServer::start(|_| xml("ok", "1.16.1", ""));
+
+        let client = SubsonicClient::builder(&server.url, "admin", "sesame")
+            .client_name("my player")
+            .user_agent("my player/1.0")
+            .connect()
+            .await
+            .unwrap();
+        client.ping().await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        for req in requests {
+            assert_eq!(req.query("c").as_deref(), Some("my player"), "{}", req.target);
+            assert_eq!(req.header("user-agent"), Some("my player/1.0"));
+        }
+
+        let client = SubsonicClient::builder(&server.url, "admin", "sesame")
+            .build()
+            .unwrap();
+        assert_eq!(client.client_name(), DEFAULT_CLIENT_NAME);
+        assert!(SubsonicClient::builder(&server.url, "admin", "sesame")
+            .client_name("")
+            .build()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn form_post() {
+        let server = MockServer::start(|req| {
+            let body = if req.target.starts_with("/rest/getOpenSubsonicExtensions") {
+                r#"<openSubsonicExtensions name="formPost"><versions>1</versions></openSubsonicExtensions>"#
+            } else {
+                ""
+            };
+            (
+                "text/xml",
+                format!(
+                    r#"<subsonic-response status="ok" version="1.16.1" openSubsonic="true">{}</subsonic-response>"#,
+                    body
+                ),
+            )
+        });
+
+        let client = SubsonicClient::builder(&server.url, "admin", "sesame")
+            .form_post(true)
+            .connect()
+            .await
+            .unwrap();
+        let ids: Vec<SongId> = (0..500).map(|x| SongId::new(format!("song-{}", x))).collect();
+        client
+            .update_playlist(&"pl 1".into(), None, None, None, &ids, &[])
+            .await
+            .unwrap();
+
+        let req = server.requests().pop().unwrap();
+        assert_eq!(req.method, "POST");
+        // nothing at all in the url
+        assert_eq!(req.target, "/rest/updatePlaylist");
+        assert_eq!(
+            req.header("content-type"),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(req.form("playlistId"), ["pl 1"]);
+        assert_eq!(req.form("songIdToAdd").len(), 500);
+        assert_eq!(req.form("u"), ["admin"]);
+        assert_eq!(req.form("t").len(), 1);
+
+        // left off, or not advertised, it's still a GET
+        let client = SubsonicClient::builder(&server.url, "admin", "sesame")
+            .connect()
+            .await
+            .unwrap();
+        client.ping().await.unwrap();
+        let req = server.requests().pop().unwrap();
+        assert_eq!(req.method, "GET");
+        assert!(req.has_query("t"));
+
+        let plain = MockServer::start(|_| xml("ok", "1.16.1", ""));
+        let client = SubsonicClient::builder(&plain.url, "admin", "sesame")
+            .form_post(true)
+            .connect()
+            .await
+            .unwrap();
+        client.ping().await.unwrap();
+        assert!(plain.requests().iter().all(|x| x.method == "GET"));
+    }
+
+    #[tokio::test]
+    async fn connect_checks_the_login() {
+        let server = MockServer::start(|_| {
+            xml(
+                "failed",
+                "1.15.0",
+                r#"<error code="40" message="Wrong username or password"/>"#,
+            )
+        });
+
+        match SubsonicClient::connect(&server.url, "admin", "wrong").await {
+            Err(Error::ServerError(SubsonicErrorCode::WrongCredentials, _)) => {}
+            x => panic!("expected a login failure, got {:?}", x),
+        }
+    }
+}
@@ -1,191 +1,313 @@
-use serde::Deserialize;
+use crate::id::*;
+use crate::timestamp::Timestamp;
+use std::borrow::Cow;
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::marker::PhantomData;
+use std::time::Duration;
 
-#[derive(Debug, PartialEq, Deserialize)]
+// the models decode from both the xml and json forms of a response
+// children are named after their element (xml) or key (json), text content is
+// the element text (xml) or the "value" key (json)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubsonicResp {
     pub status: String,
     pub version: String,
+    // OpenSubsonic servers also say what they are
+    #[serde(rename = "type")]
+    pub server_type: Option<String>,
+    pub server_version: Option<String>,
+    #[serde(default)]
+    pub open_subsonic: bool,
     #[serde(rename = "$value")]
     pub resp: Option<SubsonicInfo>,
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+// OpenSubsonic sends the extensions in xml as repeated <openSubsonicExtensions name=".."> elements
+// with no wrapper, which serde-xml-rs can't read as a list
+// so they're renamed to <openSubsonicExtension> and a wrapper is put around them
+fn wrap_extensions(body: &str) -> Cow<'_, str> {
+    const OPEN: &str = "<openSubsonicExtensions ";
+    const END: &str = "</subsonic-response>";
+    let (start, end) = match (body.find(OPEN), body.rfind(END)) {
+        (Some(start), Some(end)) if start < end => (start, end),
+        _ => return Cow::Borrowed(body),
+    };
+    let list = body[start..end]
+        .replace(OPEN, "<openSubsonicExtension ")
+        .replace("</openSubsonicExtensions>", "</openSubsonicExtension>");
+    Cow::Owned(format!(
+        "{}<openSubsonicExtensions>{}</openSubsonicExtensions>{}",
+        &body[..start],
+        list,
+        &body[end..]
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum SubsonicInfo {
-    #[serde(alias = "newestPodcasts")]
-    #[serde(alias = "nowPlaying")]
-    #[serde(alias = "randomSongs")]
-    #[serde(alias = "similarSongs")]
-    #[serde(alias = "similarSongs2")]
-    #[serde(alias = "songsByGenre")]
-    #[serde(alias = "topSongs")]
-    #[serde(alias = "videos")]
-    MediaList(GenericList<Media>),
+    // every variant is a element (xml) or key (json) of its own, so a response can be written back out
+    #[serde(serialize_with = "crate::serialize::episode")]
+    NewestPodcasts(GenericList<Media>),
+    #[serde(serialize_with = "crate::serialize::entry")]
+    NowPlaying(GenericList<Media>),
+    #[serde(serialize_with = "crate::serialize::song")]
+    RandomSongs(GenericList<Media>),
+    #[serde(serialize_with = "crate::serialize::song")]
+    SimilarSongs(GenericList<Media>),
+    #[serde(serialize_with = "crate::serialize::song")]
+    SimilarSongs2(GenericList<Media>),
+    #[serde(serialize_with = "crate::serialize::song")]
+    SongsByGenre(GenericList<Media>),
+    #[serde(serialize_with = "crate::serialize::song")]
+    TopSongs(GenericList<Media>),
+    #[serde(serialize_with = "crate::serialize::video")]
+    Videos(GenericList<Media>),
 
     Album(Album),
-    #[serde(alias = "albumList2")]
+    #[serde(serialize_with = "crate::serialize::folder_album_list")]
     AlbumList(GenericList<Album>),
+    #[serde(serialize_with = "crate::serialize::album")]
+    AlbumList2(GenericList<Album>),
     Artist(Artist),
     Artists(Artists),
+    #[serde(serialize_with = "crate::serialize::bookmark")]
     Bookmarks(GenericList<Bookmark>),
+    #[serde(serialize_with = "crate::serialize::chat_message")]
     ChatMessages(GenericList<ChatMessage>),
     Directory(Directory),
+    #[serde(serialize_with = "crate::serialize::genre")]
     Genres(GenericList<Genre>),
     Indexes(Indexes),
+    #[serde(serialize_with = "crate::serialize::internet_radio_station")]
     InternetRadioStations(GenericList<Radio>),
     JukeboxPlaylist(JukeboxPlaylist),
+    #[serde(serialize_with = "crate::serialize::music_folder")]
     MusicFolders(GenericList<MusicFolder>),
     Playlist(Playlist),
+    #[serde(serialize_with = "crate::serialize::playlist")]
     Playlists(GenericList<Playlist>),
     PlayQueue(PlayQueue),
+    #[serde(serialize_with = "crate::serialize::channel")]
     Podcasts(GenericList<Channel>),
     SearchResult(SearchResult),
-    #[serde(alias = "searchResult2")]
-    #[serde(alias = "searchResult3")]
-    #[serde(alias = "starred")]
-    #[serde(alias = "starred2")]
-    SearchOrStarList(GenericList<SubsonicInfo>),
+    #[serde(serialize_with = "crate::serialize::folder_albums")]
+    SearchResult2(SearchResult2),
+    SearchResult3(SearchResult3),
+    #[serde(serialize_with = "crate::serialize::folder_albums")]
+    Starred(Starred),
+    Starred2(Starred),
+    #[serde(serialize_with = "crate::serialize::share")]
     Shares(GenericList<Share>),
-    Song(Media),
+    // boxed, a song is by far the biggest of the models
+    Song(Box<Media>),
     User(User),
+    #[serde(serialize_with = "crate::serialize::user")]
     Users(GenericList<User>),
     VideoInfo(VideoInfo),
 
     #[serde(rename_all = "camelCase")]
     Error {
-        code: u32,
+        code: SubsonicErrorCode,
         message: Option<String>,
     },
-    #[serde(rename_all = "camelCase")]
-    JukeboxStatus {
-        current_index: u64,
-        playing: bool,
-        gain: f32,
-        position: u64,
-    },
-    #[serde(rename_all = "camelCase")]
-    License {
-        valid: bool,
-        email: String,
-        license_expires: String,
-    },
-    #[serde(rename_all = "camelCase")]
-    Lyrics {
-        artist: String,
-        title: String,
-        #[serde(rename = "$value")]
-        lyrics: String,
-    },
-    #[serde(alias = "albumInfo")]
-    #[serde(alias = "artistInfo2")]
-    #[serde(alias = "artistInfo")]
-    #[serde(rename_all = "camelCase")]
-    PublicInfo {
-        biography: Option<String>,
-        notes: Option<String>,
-        music_brainz_id: Option<String>,
-        last_fm_url: Option<String>,
-        small_image_url: Option<String>,
-        medium_image_url: Option<String>,
-        large_image_url: Option<String>,
-        #[serde(rename = "similarArtist")]
-        similar_artists: Option<Vec<Artist>>,
-    },
-    #[serde(rename_all = "camelCase")]
-    ScanStatus {
-        scanning: bool,
-        count: Option<u32>,
-    },
+    JukeboxStatus(JukeboxStatus),
+    License(License),
+    Lyrics(Lyrics),
+    #[serde(serialize_with = "crate::serialize::structured_lyrics")]
+    LyricsList(GenericList<StructuredLyrics>),
+    #[serde(serialize_with = "crate::serialize::unwrapped")]
+    OpenSubsonicExtensions(GenericList<OpenSubsonicExtension>),
+    AlbumInfo(PublicInfo),
+    ArtistInfo(PublicInfo),
+    ArtistInfo2(PublicInfo),
+    ScanStatus(ScanStatus),
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum Entrires {
-    AllowedUser(String),
-    Entry(Media),
+// the error codes a failed response can have
+// 42, 43 and 44 are from OpenSubsonic, anything not in the spec ends up in Other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
+pub enum SubsonicErrorCode {
+    Generic,
+    MissingParameter,
+    ClientTooOld,
+    ServerTooOld,
+    WrongCredentials,
+    TokenAuthNotSupported,
+    AuthMechanismNotSupported,
+    ConflictingAuthMechanisms,
+    InvalidApiKey,
+    NotAuthorized,
+    TrialExpired,
+    NotFound,
+    Other(u32),
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum IndexChoice {
-    #[serde(rename_all = "camelCase")]
-    Shortcut {
-        id: String,
-        name: String,
-    },
-    Index(Index),
-    Child(Media),
+impl From<u32> for SubsonicErrorCode {
+    fn from(code: u32) -> Self {
+        match code {
+            0 => SubsonicErrorCode::Generic,
+            10 => SubsonicErrorCode::MissingParameter,
+            20 => SubsonicErrorCode::ClientTooOld,
+            30 => SubsonicErrorCode::ServerTooOld,
+            40 => SubsonicErrorCode::WrongCredentials,
+            41 => SubsonicErrorCode::TokenAuthNotSupported,
+            42 => SubsonicErrorCode::AuthMechanismNotSupported,
+            43 => SubsonicErrorCode::ConflictingAuthMechanisms,
+            44 => SubsonicErrorCode::InvalidApiKey,
+            50 => SubsonicErrorCode::NotAuthorized,
+            60 => SubsonicErrorCode::TrialExpired,
+            70 => SubsonicErrorCode::NotFound,
+            x => SubsonicErrorCode::Other(x),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum VideoProperties {
-    #[serde(rename_all = "camelCase")]
-    AudioTrack {
-        id: String,
-        name: String,
-        language_code: String,
-    },
-    #[serde(rename_all = "camelCase")]
-    Captions { id: String, name: String },
-    #[serde(rename_all = "camelCase")]
-    Conversion { id: String, bit_rate: u32 },
+impl From<SubsonicErrorCode> for u32 {
+    fn from(code: SubsonicErrorCode) -> Self {
+        match code {
+            SubsonicErrorCode::Generic => 0,
+            SubsonicErrorCode::MissingParameter => 10,
+            SubsonicErrorCode::ClientTooOld => 20,
+            SubsonicErrorCode::ServerTooOld => 30,
+            SubsonicErrorCode::WrongCredentials => 40,
+            SubsonicErrorCode::TokenAuthNotSupported => 41,
+            SubsonicErrorCode::AuthMechanismNotSupported => 42,
+            SubsonicErrorCode::ConflictingAuthMechanisms => 43,
+            SubsonicErrorCode::InvalidApiKey => 44,
+            SubsonicErrorCode::NotAuthorized => 50,
+            SubsonicErrorCode::TrialExpired => 60,
+            SubsonicErrorCode::NotFound => 70,
+            SubsonicErrorCode::Other(x) => x,
+        }
+    }
+}
+
+impl std::fmt::Display for SubsonicErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", u32::from(*self), match *self {
+            SubsonicErrorCode::Generic => "generic error",
+            SubsonicErrorCode::MissingParameter => "required parameter is missing",
+            SubsonicErrorCode::ClientTooOld => "client must upgrade",
+            SubsonicErrorCode::ServerTooOld => "server must upgrade",
+            SubsonicErrorCode::WrongCredentials => "wrong username or password",
+            SubsonicErrorCode::TokenAuthNotSupported => "token authentication not supported",
+            SubsonicErrorCode::AuthMechanismNotSupported => "authentication mechanism not supported",
+            SubsonicErrorCode::ConflictingAuthMechanisms => "multiple conflicting authentication mechanisms provided",
+            SubsonicErrorCode::InvalidApiKey => "invalid api key",
+            SubsonicErrorCode::NotAuthorized => "user is not authorized for the given operation",
+            SubsonicErrorCode::TrialExpired => "trial period is over",
+            SubsonicErrorCode::NotFound => "the requested data was not found",
+            SubsonicErrorCode::Other(_) => "unknown error",
+        })
+    }
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+impl SubsonicResp {
+    // the error of a failed response
+    // a failed response without a error element is treated as a generic error
+    pub fn failure(&self) -> Option<(SubsonicErrorCode, String)> {
+        match &self.resp {
+            Some(SubsonicInfo::Error { code, message }) => {
+                Some((*code, message.clone().unwrap_or_default()))
+            }
+            _ if self.status == "failed" => Some((SubsonicErrorCode::Generic, String::new())),
+            _ => None,
+        }
+    }
+
+    pub fn from_xml(body: &str) -> Result<Self, serde_xml_rs::Error> {
+        serde_xml_rs::from_str(&wrap_extensions(body))
+    }
+
+    // the json form is a subsonic-response object holding the status, version, etc,
+    // along with a single key for the payload, ex: {"subsonic-response": {"status": "ok", "album": {...}}}
+    pub fn from_json(body: &str) -> Result<Self, serde_json::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            #[serde(rename = "subsonic-response")]
+            fields: serde_json::Map<String, serde_json::Value>,
+        }
+
+        let mut fields = serde_json::from_str::<Wrapper>(body)?.fields;
+        // everything other than the payload is a plain value
+        let payload = fields
+            .iter()
+            .find(|(_, v)| v.is_object() || v.is_array())
+            .map(|(k, _)| k.clone())
+            .and_then(|k| fields.remove_entry(&k));
+
+        let mut resp: SubsonicResp = serde_json::from_value(serde_json::Value::Object(fields))?;
+        resp.resp = payload
+            .map(|payload| {
+                serde_json::from_value(serde_json::Value::Object(
+                    std::iter::once(payload).collect(),
+                ))
+            })
+            .transpose()?;
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Album {
-    pub id: String,
-    #[serde(alias = "name")]
+    pub id: AlbumId,
+    // "name" for the id3 albums, "title" for the folder based ones (getAlbumList)
+    // written back as "name", except in the folder based lists (see serialize's folder_albums)
+    #[serde(rename(serialize = "name", deserialize = "title"), alias = "name")]
     pub title: String,
 
     pub artist: Option<String>,
-    pub artist_id: Option<u64>,
-    pub average_rating: Option<String>,
+    pub artist_id: Option<ArtistId>,
+    pub average_rating: Option<f64>,
     pub cover_art: Option<String>,
-    pub created: Option<String>,
-    pub duration: Option<u64>,
-    pub parent: Option<String>,
+    pub created: Option<Timestamp>,
+    #[serde(default, with = "seconds")]
+    pub duration: Option<Duration>,
+    pub parent: Option<DirectoryId>,
     pub song_count: Option<u32>,
-    pub user_rating: Option<String>,
-    #[serde(rename = "$value")]
+    pub user_rating: Option<u32>,
+    #[serde(rename = "song")]
     pub songs: Option<Vec<Media>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Artist {
-    pub id: String,
+    pub id: ArtistId,
     pub name: String,
     pub album_count: Option<u32>,
     pub cover_art: Option<String>,
-    pub starred: Option<String>,
+    pub starred: Option<Timestamp>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Artists {
     pub ignored_articles: Option<String>,
-    #[serde(rename = "$value")]
+    #[serde(rename = "index")]
     pub indexes: Option<Vec<Index>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Bookmark {
     pub position: u64,
     pub username: String,
     pub comment: Option<String>,
-    pub created: Option<String>,
-    pub changed: Option<String>,
-    #[serde(rename = "$value")]
-    pub entries: Option<Vec<Media>>,
+    pub created: Option<Timestamp>,
+    pub changed: Option<Timestamp>,
+    pub entry: Media,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Channel {
-    pub id: String,
+    pub id: PodcastChannelId,
     pub status: String,
     pub url: String,
 
@@ -194,122 +316,293 @@ pub struct Channel {
     pub error_message: Option<String>,
     pub original_image_url: Option<String>,
     pub title: Option<String>,
-    #[serde(rename = "$value")]
+    #[serde(rename = "episode")]
     pub episodes: Option<Vec<Media>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChatMessage {
     pub username: String,
-    pub time: u64,
+    pub time: Timestamp,
     pub message: String,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Directory {
-    id: String,
-    name: String,
-    starred: Option<String>,
-    parent: Option<String>,
-    #[serde(rename = "$value")]
-    children: Option<Vec<Media>>,
+    pub id: DirectoryId,
+    pub name: String,
+    pub starred: Option<Timestamp>,
+    pub parent: Option<DirectoryId>,
+    #[serde(rename = "child")]
+    pub children: Option<Vec<Media>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
-#[serde(rename_all = "camelCase")]
+// a wrapper element around a list, ex: <genres><genre/>...</genres> or {"genres": {"genre": [...]}}
+// some OpenSubsonic json lists skip the wrapper, ex: {"openSubsonicExtensions": [...]}
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct GenericList<T> {
-    #[serde(rename = "$value")]
     pub items: Option<Vec<T>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+// the name of the items doesn't matter, so every key of the wrapper is read as items
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for GenericList<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ListVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for ListVisitor<T> {
+            type Value = GenericList<T>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a list of elements")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut items: Option<Vec<T>> = None;
+                while map.next_key::<String>()?.is_some() {
+                    let more: Vec<T> = map.next_value()?;
+                    items.get_or_insert_with(Vec::new).extend(more);
+                }
+                Ok(GenericList { items })
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut items = Vec::new();
+                while let Some(x) = seq.next_element()? {
+                    items.push(x);
+                }
+                Ok(GenericList { items: Some(items) })
+            }
+        }
+
+        deserializer.deserialize_any(ListVisitor(PhantomData))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Genre {
     pub song_count: u32,
     pub album_count: u32,
-    #[serde(rename = "$value")]
+    #[serde(rename(serialize = "value", deserialize = "$value"), alias = "value")]
     pub name: String,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Indexes {
     pub last_modified: u64,
     pub ignored_articles: Option<String>,
-    #[serde(rename = "$value")]
-    pub list: Option<Vec<IndexChoice>>,
+    #[serde(rename = "shortcut")]
+    pub shortcuts: Option<Vec<Artist>>,
+    #[serde(rename = "index")]
+    pub indexes: Option<Vec<Index>>,
+    #[serde(rename = "child")]
+    pub children: Option<Vec<Media>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Index {
     pub name: String,
-    #[serde(rename = "$value")]
+    #[serde(rename = "artist")]
     pub artists: Option<Vec<Artist>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct JukeboxPlaylist {
     pub current_index: u64,
     pub playing: bool,
-    pub gain: f32,
+    pub gain: f64,
     pub position: u64,
-    #[serde(rename = "$value")]
+    #[serde(rename = "entry")]
     pub list: Option<Vec<Media>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Media {
-    pub id: String,
+    // a SongId for what's playable, for the rest (folders, podcast episodes) see directory_id and episode_id
+    pub id: SongId,
     pub is_dir: bool,
     #[serde(alias = "name")]
     pub title: String,
 
     pub album: Option<String>,
-    pub album_id: Option<u64>,
+    pub album_artist: Option<String>,
+    pub album_id: Option<AlbumId>,
     pub artist: Option<String>,
-    pub artist_id: Option<u64>,
+    pub artist_id: Option<ArtistId>,
+    pub average_rating: Option<f64>,
+    pub bit_depth: Option<u32>,
     pub bit_rate: Option<u32>,
-    pub channel_id: Option<u32>,
+    pub bpm: Option<u32>,
+    pub channel_count: Option<u32>,
+    pub channel_id: Option<PodcastChannelId>,
+    pub comment: Option<String>,
     pub description: Option<String>,
     pub content_type: Option<String>,
     pub cover_art: Option<String>,
-    pub created: Option<String>,
-    pub duration: Option<u32>,
+    pub created: Option<Timestamp>,
+    pub disc_number: Option<u32>,
+    pub display_album_artist: Option<String>,
+    pub display_artist: Option<String>,
+    pub display_composer: Option<String>,
+    #[serde(default, with = "seconds")]
+    pub duration: Option<Duration>,
+    // "explicit", "clean" or empty
+    pub explicit_status: Option<String>,
     pub genre: Option<String>,
     pub is_video: Option<bool>,
+    pub media_type: Option<String>,
     pub minutes_ago: Option<u32>,
-    pub parent: Option<String>,
+    pub music_brainz_id: Option<String>,
+    pub parent: Option<DirectoryId>,
     pub path: Option<String>,
+    pub play_count: Option<u64>,
+    pub played: Option<Timestamp>,
     pub player_id: Option<u32>,
     pub player_name: Option<String>,
-    pub publish_date: Option<String>,
-    pub size: Option<u32>,
+    pub publish_date: Option<Timestamp>,
+    pub replay_gain: Option<ReplayGain>,
+    pub sampling_rate: Option<u32>,
+    // u64, videos and hi res flacs go past 4GB
+    pub size: Option<u64>,
+    pub sort_name: Option<String>,
+    pub starred: Option<Timestamp>,
     pub status: Option<String>,
-    pub stream_id: Option<u32>,
+    pub stream_id: Option<SongId>,
     pub suffix: Option<String>,
+    pub track: Option<u32>,
     pub transcoded_content_type: Option<String>,
     pub transcoded_suffix: Option<String>,
     pub r#type: Option<String>,
+    pub user_rating: Option<u32>,
     pub username: Option<String>,
     pub year: Option<u32>,
+
+    // OpenSubsonic lists, these are repeated elements in xml
+    pub genres: Option<Vec<ItemGenre>>,
+    pub artists: Option<Vec<Artist>>,
+    pub album_artists: Option<Vec<Artist>>,
+    pub contributors: Option<Vec<Contributor>>,
+    pub moods: Option<Vec<String>>,
+    pub isrc: Option<Vec<String>>,
+}
+
+// a genre as tagged on a song, see Genre for the ones getGenres lists
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemGenre {
+    pub name: String,
+}
+
+// someone other than the artist who worked on a song, ex: a composer
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contributor {
+    pub role: String,
+    pub sub_role: Option<String>,
+    pub artist: Artist,
+}
+
+// gains are in dB
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGain {
+    pub track_gain: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_peak: Option<f64>,
+    pub base_gain: Option<f64>,
+    pub fallback_gain: Option<f64>,
+}
+
+impl Media {
+    // podcast episodes come back as media, with the episode id as their id
+    pub fn episode_id(&self) -> Option<PodcastEpisodeId> {
+        self.channel_id
+            .as_ref()
+            .map(|_| PodcastEpisodeId::from(self.id.as_str()))
+    }
+
+    // the children of a directory that are folders themselves, ready for get_music_directory
+    pub fn directory_id(&self) -> Option<DirectoryId> {
+        self.is_dir.then(|| DirectoryId::from(self.id.as_str()))
+    }
+
+    // for get_artist_info and get_similar_songs, a folder or a song depending on is_dir
+    pub fn folder_item_id(&self) -> FolderItemId {
+        match self.directory_id() {
+            Some(x) => FolderItemId::Directory(x),
+            None => FolderItemId::Song(self.id.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JukeboxStatus {
+    pub current_index: u64,
+    pub playing: bool,
+    pub gain: f64,
+    pub position: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct License {
+    pub valid: bool,
+    pub email: String,
+    pub license_expires: Timestamp,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lyrics {
+    pub artist: String,
+    pub title: String,
+    #[serde(rename(serialize = "value", deserialize = "$value"), alias = "value")]
+    pub lyrics: String,
+}
+
+// one set of lyrics from getLyricsBySongId, synced lyrics have a start time on every line
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuredLyrics {
+    pub lang: String,
+    pub synced: bool,
+    pub display_artist: Option<String>,
+    pub display_title: Option<String>,
+    // in milliseconds, to be added to every start
+    pub offset: Option<i64>,
+    #[serde(rename = "line")]
+    pub lines: Option<Vec<LyricLine>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricLine {
+    // in milliseconds
+    pub start: Option<u64>,
+    #[serde(rename(serialize = "value", deserialize = "$value"), alias = "value", default)]
+    pub value: String,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MusicFolder {
+    #[serde(deserialize_with = "string_or_number")]
     pub id: String,
     pub name: String,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Playlist {
-    pub id: String,
+    pub id: PlaylistId,
     pub name: String,
     pub owner: String,
     pub public: bool,
@@ -317,58 +610,90 @@ pub struct Playlist {
 
     pub comment: Option<String>,
     pub cover_art: Option<String>,
-    pub created: Option<String>,
-    #[serde(rename = "$value")]
-    pub entries: Option<Vec<Entrires>>,
+    pub created: Option<Timestamp>,
+    #[serde(rename = "allowedUser")]
+    pub allowed_users: Option<Vec<String>>,
+    #[serde(rename = "entry")]
+    pub entries: Option<Vec<Media>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PlayQueue {
-    current: u64,
-    position: u64,
-    username: String,
+    pub current: Option<SongId>,
+    pub position: u64,
+    pub username: String,
 
-    changed: Option<String>,
-    changed_by: Option<String>,
-    #[serde(rename = "$value")]
-    entries: Option<Vec<Media>>,
+    pub changed: Option<Timestamp>,
+    pub changed_by: Option<String>,
+    #[serde(rename = "entry")]
+    pub entries: Option<Vec<Media>>,
+}
+
+impl PlayQueue {
+    // the entry the queue is at, if it's in the list
+    pub fn current_entry(&self) -> Option<&Media> {
+        let current = self.current.as_ref()?;
+        self.entries.as_ref()?.iter().find(|x| &x.id == current)
+    }
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicInfo {
+    pub biography: Option<String>,
+    pub notes: Option<String>,
+    pub music_brainz_id: Option<String>,
+    pub last_fm_url: Option<String>,
+    pub small_image_url: Option<String>,
+    pub medium_image_url: Option<String>,
+    pub large_image_url: Option<String>,
+    #[serde(rename = "similarArtist")]
+    pub similar_artists: Option<Vec<Artist>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Radio {
-    pub id: String,
+    pub id: RadioStationId,
     pub name: String,
     pub stream_url: String,
     pub home_page_url: Option<String>,
 }
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResult {
-    offset: Option<u32>,
-    total: Option<u32>,
-    #[serde(rename = "$value")]
-    results: Option<Vec<Media>>,
+    pub offset: Option<u32>,
+    #[serde(rename = "totalHits")]
+    pub total: Option<u32>,
+    #[serde(rename = "match")]
+    pub results: Option<Vec<Media>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanStatus {
+    pub scanning: bool,
+    pub count: Option<u32>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Share {
-    pub created: String,
-    pub id: String,
+    pub created: Timestamp,
+    pub id: ShareId,
     pub url: String,
     pub username: String,
     pub visit_count: u32,
 
     pub description: Option<String>,
-    pub last_visited: Option<String>,
-    pub expires: Option<String>,
-    #[serde(rename = "$value")]
+    pub last_visited: Option<Timestamp>,
+    pub expires: Option<Timestamp>,
+    #[serde(rename = "entry")]
     pub entries: Option<Vec<Media>>,
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
     pub admin_role: bool,
@@ -386,17 +711,216 @@ pub struct User {
     pub username: String,
 
     pub email: Option<String>,
-    #[serde(rename = "$value")]
+    #[serde(rename = "folder")]
     pub folders: Option<Vec<u32>>,
 }
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoInfo {
-    id: String,
-    #[serde(rename = "$value")]
-    properties: Option<Vec<VideoProperties>>,
+    pub id: SongId,
+    pub captions: Option<Vec<Captions>>,
+    #[serde(rename = "audioTrack")]
+    pub audio_tracks: Option<Vec<AudioTrack>>,
+    #[serde(rename = "conversion")]
+    pub conversions: Option<Vec<Conversion>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Captions {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTrack {
+    pub id: String,
+    pub name: Option<String>,
+    pub language_code: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversion {
+    pub id: String,
+    pub bit_rate: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenSubsonicExtension {
+    pub name: String,
+    #[serde(default)]
+    pub versions: Vec<u32>,
+}
+
+// the starred and starred2 lists, split up by what kind of thing was starred
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Starred {
+    #[serde(rename = "artist", default)]
+    pub artists: Vec<Artist>,
+    #[serde(rename = "album", default)]
+    pub albums: Vec<Album>,
+    #[serde(rename = "song", default)]
+    pub songs: Vec<Media>,
 }
 
+// what search3 found, each kind is paged by its own count and offset args
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult3 {
+    #[serde(rename = "artist", default)]
+    pub artists: Vec<Artist>,
+    #[serde(rename = "album", default)]
+    pub albums: Vec<Album>,
+    #[serde(rename = "song", default)]
+    pub songs: Vec<Media>,
+}
+
+// search2 has the same layout, only it searches by folder instead of by tag
+pub type SearchResult2 = SearchResult3;
+
+// ids are strings, but some servers send the numeric ones as json numbers
+pub(crate) fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    struct IdVisitor;
+
+    impl<'de> Visitor<'de> for IdVisitor {
+        type Value = String;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a string or number")
+        }
+
+        fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(v.to_string())
+        }
+
+        fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(v.to_string())
+        }
+
+        fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(v.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(IdVisitor)
+}
+
+// durations are sent as whole seconds
+mod seconds {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        duration.map(|x| x.as_secs()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_secs))
+    }
+}
+
+impl<T> From<GenericList<T>> for Vec<T> {
+    fn from(list: GenericList<T>) -> Self {
+        list.items.unwrap_or_default()
+    }
+}
+
+impl<T> From<Vec<T>> for GenericList<T> {
+    fn from(items: Vec<T>) -> Self {
+        GenericList { items: Some(items) }
+    }
+}
+
+// pulls the typed value out of a response
+// None means the response held something other than what the endpoint should return
+pub trait FromSubsonicInfo: Sized {
+    fn from_info(info: Option<SubsonicInfo>) -> Option<Self>;
+}
+
+// for the endpoints that return an empty response
+impl FromSubsonicInfo for () {
+    fn from_info(info: Option<SubsonicInfo>) -> Option<Self> {
+        match info {
+            None => Some(()),
+            Some(_) => None,
+        }
+    }
+}
+
+// for the endpoints that only sometimes return something (ex: create_playlist before 1.14.0)
+impl<T: FromSubsonicInfo> FromSubsonicInfo for Option<T> {
+    fn from_info(info: Option<SubsonicInfo>) -> Option<Self> {
+        match info {
+            None => Some(None),
+            info => T::from_info(info).map(Some),
+        }
+    }
+}
+
+macro_rules! from_info {
+    ($($($variant:ident)|+ => $ret:ty),* $(,)?) => {$(
+        impl FromSubsonicInfo for $ret {
+            fn from_info(info: Option<SubsonicInfo>) -> Option<Self> {
+                match info {
+                    $(Some(SubsonicInfo::$variant(x)) => Some(x.into()),)+
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+impl FromSubsonicInfo for Media {
+    fn from_info(info: Option<SubsonicInfo>) -> Option<Self> {
+        match info {
+            Some(SubsonicInfo::Song(x)) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+from_info!(
+    NewestPodcasts
+        | NowPlaying
+        | RandomSongs
+        | SimilarSongs
+        | SimilarSongs2
+        | SongsByGenre
+        | TopSongs
+        | Videos => Vec<Media>,
+    Album => Album,
+    AlbumList | AlbumList2 => Vec<Album>,
+    Artist => Artist,
+    Artists => Artists,
+    Bookmarks => Vec<Bookmark>,
+    ChatMessages => Vec<ChatMessage>,
+    Directory => Directory,
+    Genres => Vec<Genre>,
+    Indexes => Indexes,
+    InternetRadioStations => Vec<Radio>,
+    JukeboxPlaylist => JukeboxPlaylist,
+    MusicFolders => Vec<MusicFolder>,
+    Playlist => Playlist,
+    Playlists => Vec<Playlist>,
+    PlayQueue => PlayQueue,
+    Podcasts => Vec<Channel>,
+    SearchResult => SearchResult,
+    SearchResult2 | SearchResult3 => SearchResult3,
+    Starred | Starred2 => Starred,
+    Shares => Vec<Share>,
+    User => User,
+    Users => Vec<User>,
+    VideoInfo => VideoInfo,
+    JukeboxStatus => JukeboxStatus,
+    License => License,
+    Lyrics => Lyrics,
+    LyricsList => Vec<StructuredLyrics>,
+    OpenSubsonicExtensions => Vec<OpenSubsonicExtension>,
+    AlbumInfo | ArtistInfo | ArtistInfo2 => PublicInfo,
+    ScanStatus => ScanStatus,
+);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -435,4 +959,241 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn typed_responses() {
+        let starred = des!(
+            r#"<subsonic-response xmlns="http://subsonic.org/restapi" status="ok" version="1.8.0">
+                <starred2>
+                    <artist name="ABBA" id="1" coverArt="ar-1" albumCount="3"/>
+                    <album id="10" name="Arrival" artist="ABBA" artistId="1" songCount="2" duration="300"/>
+                    <song id="100" parent="10" title="Dancing Queen" isDir="false"/>
+                    <song id="101" parent="10" title="Money, Money, Money" isDir="false"/>
+                </starred2>
+            </subsonic-response>"#
+        );
+        let starred = Starred::from_info(starred.resp).unwrap();
+        assert_eq!(starred.artists.len(), 1);
+        assert_eq!(starred.albums[0].title, "Arrival");
+        assert_eq!(starred.songs.len(), 2);
+
+        let empty = des!(r#"<subsonic-response status="ok" version="1.16.1"></subsonic-response>"#);
+        assert_eq!(<()>::from_info(empty.resp), Some(()));
+
+        let genres = des!(
+            r#"<subsonic-response status="ok" version="1.10.2">
+                <genres>
+                    <genre songCount="28" albumCount="6">Electronic</genre>
+                    <genre songCount="6" albumCount="2">Hard Rock</genre>
+                </genres>
+            </subsonic-response>"#
+        );
+        // a list of genres isn't a album
+        assert_eq!(Album::from_info(genres.resp), None);
+    }
+
+    #[test]
+    fn failed_responses() {
+        let failed = des!(
+            r#"<subsonic-response xmlns="http://subsonic.org/restapi" status="failed" version="1.1.1">
+                <error code="40" message="Wrong username or password"/>
+            </subsonic-response>"#
+        );
+        assert_eq!(
+            failed.failure(),
+            Some((
+                SubsonicErrorCode::WrongCredentials,
+                "Wrong username or password".to_string()
+            ))
+        );
+
+        let unknown = des!(
+            r#"<subsonic-response status="failed" version="1.16.1"><error code="99"/></subsonic-response>"#
+        );
+        assert_eq!(
+            unknown.failure(),
+            Some((SubsonicErrorCode::Other(99), String::new()))
+        );
+
+        let bare = des!(r#"<subsonic-response status="failed" version="1.16.1"/>"#);
+        assert_eq!(
+            bare.failure(),
+            Some((SubsonicErrorCode::Generic, String::new()))
+        );
+
+        let ok = des!(r#"<subsonic-response status="ok" version="1.16.1"/>"#);
+        assert_eq!(ok.failure(), None);
+
+        for code in [0, 10, 20, 30, 40, 41, 42, 43, 44, 50, 60, 70, 1234] {
+            assert_eq!(u32::from(SubsonicErrorCode::from(code)), code);
+        }
+    }
+
+    fn fixture(name: &str) -> (SubsonicResp, SubsonicResp) {
+        let read = |ext: &str| read_to_string(format!("./test/fixtures/{}.{}", name, ext)).unwrap();
+        (
+            SubsonicResp::from_xml(&read("xml")).unwrap(),
+            SubsonicResp::from_json(&read("json")).unwrap(),
+        )
+    }
+
+    #[test]
+    fn xml_and_json_match() {
+        for name in [
+            "album",
+            "artists",
+            "bookmarks",
+            "error",
+            "genres",
+            "indexes",
+            "lyrics",
+            "lyricsList",
+            "musicFolders",
+            "openSubsonicExtensions",
+            "ping",
+            "playlist",
+            "podcasts",
+            "searchResult",
+            "searchResult3",
+            "song",
+            "starred2",
+            "user",
+            "videoInfo",
+        ] {
+            let (xml, json) = fixture(name);
+            assert_eq!(xml, json, "{} decodes differently", name);
+            assert_eq!(xml.resp.is_none(), name == "ping", "{} has no payload", name);
+        }
+    }
+
+    #[test]
+    fn fixture_contents() {
+        let album = Album::from_info(fixture("album").1.resp).unwrap();
+        assert_eq!(album.title, "High Voltage");
+        assert_eq!(album.average_rating, Some(4.5));
+        let songs = album.songs.unwrap();
+        assert_eq!(songs.len(), 2);
+        // a numeric id in json is read the same as a string one
+        assert_eq!(songs[1].album_id, Some(AlbumId::from("11053")));
+
+        let genres = Vec::<Genre>::from_info(fixture("genres").0.resp).unwrap();
+        assert_eq!(genres[2].name, "R&B");
+
+        let indexes = Indexes::from_info(fixture("indexes").1.resp).unwrap();
+        assert_eq!(indexes.shortcuts.unwrap().len(), 2);
+        assert_eq!(indexes.indexes.unwrap()[0].artists.as_ref().unwrap().len(), 2);
+        assert_eq!(indexes.children.unwrap()[0].title, "Dancing Queen");
+
+        let playlist = Playlist::from_info(fixture("playlist").0.resp).unwrap();
+        assert_eq!(playlist.allowed_users.unwrap(), ["sindre", "john"]);
+        assert_eq!(playlist.entries.unwrap().len(), 2);
+
+        let (extensions, _) = fixture("openSubsonicExtensions");
+        assert!(extensions.open_subsonic);
+        assert_eq!(extensions.server_type.as_deref(), Some("navidrome"));
+        assert_eq!(extensions.server_version.as_deref(), Some("0.53.3 (13af8ed4)"));
+        let extensions = Vec::<OpenSubsonicExtension>::from_info(extensions.resp).unwrap();
+        assert_eq!(extensions.len(), 3);
+        assert_eq!(extensions[2].name, "songLyrics");
+        assert_eq!(extensions[2].versions, [1, 2]);
+
+        let lyrics = Vec::<StructuredLyrics>::from_info(fixture("lyricsList").0.resp).unwrap();
+        assert!(lyrics[0].synced);
+        assert_eq!(lyrics[0].offset, Some(-100));
+        let lines = lyrics[0].lines.as_ref().unwrap();
+        assert_eq!(lines[1].start, Some(2000));
+        assert_eq!(lines[1].value, "Grating me");
+        assert_eq!(lyrics[1].lines.as_ref().unwrap()[0].start, None);
+
+        // a plain subsonic server
+        assert!(!fixture("album").0.open_subsonic);
+
+        let (_, error) = fixture("error");
+        assert_eq!(
+            error.failure(),
+            Some((
+                SubsonicErrorCode::WrongCredentials,
+                "Wrong username or password".to_string()
+            ))
+        );
+    }
+
+    // starred and search results each get their own model, split up by kind
+    #[test]
+    fn starred_and_search_results() {
+        let starred = Starred::from_info(fixture("starred2").1.resp).unwrap();
+        assert_eq!(starred.artists.len(), 1);
+        assert_eq!(starred.albums.len(), 1);
+        assert_eq!(starred.songs.len(), 2);
+        // the starred list isn't a search result, or the other way around
+        assert!(SearchResult3::from_info(fixture("starred2").0.resp).is_none());
+        assert!(Starred::from_info(fixture("searchResult3").0.resp).is_none());
+
+        let found = SearchResult3::from_info(fixture("searchResult3").0.resp).unwrap();
+        assert_eq!(found.artists[1].name, "The Beatles Tribute Band");
+        assert_eq!(found.albums[0].id, AlbumId::from("11047"));
+        assert_eq!(found.songs[1].title, "Something");
+        assert_eq!(found.songs[1].artist_id, Some(ArtistId::from("505")));
+
+        let found = SearchResult::from_info(fixture("searchResult").0.resp).unwrap();
+        assert_eq!((found.offset, found.total), (Some(0), Some(2)));
+        assert_eq!(found.results.unwrap()[0].title, "Come Together");
+    }
+
+    // the OpenSubsonic additions to a song, see test/fixtures/song.*
+    #[test]
+    fn open_subsonic_song_fields() {
+        let song = Media::from_info(fixture("song").0.resp).unwrap();
+        assert_eq!((song.disc_number, song.track), (Some(1), Some(3)));
+        assert_eq!((song.user_rating, song.average_rating), (Some(5), Some(4.5)));
+        assert_eq!(song.size, Some(5 << 30));
+        assert_eq!(song.play_count, Some(48));
+        assert_eq!(song.duration, Some(Duration::from_secs(1227)));
+        assert_eq!(song.starred.unwrap().to_string(), "2023-03-27T09:45:27Z");
+        assert_eq!(song.created.unwrap().as_millis(), 1_678_816_282_112);
+        assert_eq!(song.genres.unwrap()[1].name, "Alternative");
+        assert_eq!(song.album_artists.unwrap()[0].name, "Muse");
+        let contributors = song.contributors.unwrap();
+        assert_eq!(contributors[1].sub_role.as_deref(), Some("Bass"));
+        assert_eq!(contributors[0].artist.name, "Matthew Bellamy");
+        assert_eq!(song.replay_gain.unwrap().album_peak, Some(9.0));
+        assert_eq!(song.moods.unwrap(), ["sad", "energetic"]);
+        assert_eq!(song.isrc.unwrap(), ["GBAHT0300002"]);
+    }
+
+    // the models are plain data: public fields, Clone, Hash where there are no floats, and Serialize
+    #[test]
+    fn models_are_plain_data() {
+        let directory = Directory::from_info(
+            des!(r#"<subsonic-response status="ok" version="1.16.1">
+                <directory id="10" name="Arrival" starred="2013-11-02T12:30:00">
+                    <child id="100" parent="10" title="Dancing Queen" isDir="false" duration="230"/>
+                </directory>
+            </subsonic-response>"#)
+            .resp,
+        )
+        .unwrap();
+        assert_eq!(directory.name, "Arrival");
+        assert_eq!(directory.children.as_ref().unwrap()[0].duration, Some(Duration::from_secs(230)));
+
+        // models can be kept, compared and sent on
+        let json = serde_json::to_string(&directory).unwrap();
+        assert_eq!(serde_json::from_str::<Directory>(&json).unwrap(), directory.clone());
+        let artists = Starred::from_info(fixture("starred2").0.resp).unwrap().artists;
+        let unique: std::collections::HashSet<Artist> = artists.iter().chain(&artists).cloned().collect();
+        assert_eq!(unique.len(), 1);
+
+        let queue = PlayQueue::from_info(
+            des!(r#"<subsonic-response status="ok" version="1.16.1">
+                <playQueue current="101" position="13000" username="admin" changed="2015-02-18T20:06:25.581Z" changedBy="android">
+                    <entry id="100" isDir="false" title="Dancing Queen"/>
+                    <entry id="101" isDir="false" title="Money, Money, Money"/>
+                </playQueue>
+            </subsonic-response>"#)
+            .resp,
+        )
+        .unwrap();
+        assert_eq!(queue.current_entry().unwrap().title, "Money, Money, Money");
+        assert_eq!(queue.changed_by.as_deref(), Some("android"));
+    }
 }
@@ -0,0 +1,163 @@
+use crate::deserialize::SubsonicErrorCode;
+use crate::{ApiVersion, Extension};
+use thiserror::Error;
+
+// how much of a body is kept when it can't be decoded
+const SNIPPET_LEN: usize = 256;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    // the request never got a usable http response (connection, tls, http status, etc)
+    #[error("request to {endpoint} failed: {source}")]
+    Http {
+        endpoint: &'static str,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("request to {endpoint} timed out")]
+    Timeout {
+        endpoint: &'static str,
+        #[source]
+        source: reqwest::Error,
+    },
+    // the server answered, but not with anything that could be read
+    #[error(
+        "could not decode the response from {endpoint}: {reason} (body starts with {snippet:?})"
+    )]
+    Decode {
+        endpoint: &'static str,
+        reason: String,
+        snippet: String,
+    },
+    #[error("the server returned error {0}: {1}")]
+    ServerError(SubsonicErrorCode, String),
+    #[error("{endpoint} returned something other than a {expected}")]
+    UnexpectedResponse {
+        endpoint: &'static str,
+        expected: &'static str,
+    },
+    #[error(
+        "the server is not new enough to use this api, server is {0} while api requested is {1}"
+    )]
+    APIVersionMismatch(ApiVersion, ApiVersion),
+    #[error(
+        "the server is not new enough to use this arg, server is {0} while arg required is {1}"
+    )]
+    ArgsVersionMismatch(ApiVersion, ApiVersion),
+    #[error("the server does not advertise the {0} OpenSubsonic extension")]
+    ExtensionNotSupported(Extension),
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+impl Error {
+    // sorts a reqwest error into transport or timeout
+    // the login params are masked on the url the error carries
+    pub(crate) fn http(endpoint: &'static str, mut source: reqwest::Error) -> Self {
+        if let Some(url) = source.url_mut() {
+            crate::auth::redact_url(url);
+        }
+        if source.is_timeout() {
+            Error::Timeout { endpoint, source }
+        } else {
+            Error::Http { endpoint, source }
+        }
+    }
+
+    pub(crate) fn decode(endpoint: &'static str, reason: impl ToString, body: &str) -> Self {
+        Error::Decode {
+            endpoint,
+            reason: reason.to_string(),
+            snippet: crate::auth::redact_text(&body.chars().take(SNIPPET_LEN).collect::<String>()),
+        }
+    }
+
+    // worth trying again later, the server or the network is having a bad time
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Timeout { .. } => true,
+            Error::Http { source, .. } => {
+                source.is_connect() || source.status().is_some_and(|x| x.is_server_error())
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_keeps_a_snippet() {
+        let body = "<html>".to_string() + &"a".repeat(1000);
+        match Error::decode("getAlbum", "expected subsonic-response", &body) {
+            Error::Decode {
+                endpoint,
+                reason,
+                snippet,
+            } => {
+                assert_eq!(endpoint, "getAlbum");
+                assert_eq!(reason, "expected subsonic-response");
+                assert_eq!(snippet.chars().count(), SNIPPET_LEN);
+                assert!(snippet.starts_with("<html>"));
+            }
+            x => panic!("expected a decode error, got {:?}", x),
+        }
+    }
+
+    #[tokio::test]
+    async fn http_errors_hide_the_login() {
+        // nothing listens on port 1
+        let client = crate::SubsonicClient::builder("http://127.0.0.1:1/", "admin", "")
+            .auth(crate::Auth::ApiKey("key123".into()))
+            .build()
+            .unwrap();
+        let err = client.ping().await.unwrap_err();
+        assert!(matches!(err, Error::Http { .. }), "{:?}", err);
+        let mut text = format!("{} {:?}", err, err);
+        let mut source = std::error::Error::source(&err);
+        while let Some(x) = source {
+            text += &x.to_string();
+            source = x.source();
+        }
+        assert!(text.contains("apiKey=***"), "{}", text);
+        assert!(!text.contains("key123"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn http_statuses_are_errors() {
+        // a gateway in front of the server, not the server itself
+        let server = crate::mock::MockServer::start_with_status(|_| {
+            (503, "text/html", "<html>Bad gateway</html>".to_string())
+        });
+        let client = crate::SubsonicClient::builder(&server.url, "admin", "sesame")
+            .build()
+            .unwrap();
+        let err = client.ping().await.unwrap_err();
+        assert!(matches!(&err, Error::Http { endpoint: "ping", .. }), "{:?}", err);
+        assert!(err.is_transient());
+
+        let err = crate::SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap_err();
+        assert!(matches!(&err, Error::Http { .. }), "{:?}", err);
+        assert!(err.is_transient());
+    }
+
+    #[test]
+    fn server_errors_are_matchable() {
+        let err = Error::ServerError(SubsonicErrorCode::WrongCredentials, "bad".to_string());
+        assert!(matches!(
+            err,
+            Error::ServerError(SubsonicErrorCode::WrongCredentials, _)
+        ));
+        assert!(!err.is_transient());
+        assert_eq!(
+            err.to_string(),
+            "the server returned error 40 (wrong username or password): bad"
+        );
+    }
+}
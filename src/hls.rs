@@ -0,0 +1,361 @@
+use super::*;
+use std::time::Duration;
+
+// a parsed hls.m3u8
+// the server sends a master playlist when more than one bit rate is asked for,
+// and a media playlist (the actual segments) otherwise
+#[derive(Debug, Clone, PartialEq)]
+pub enum HlsPlaylist {
+    Master(Vec<HlsVariant>),
+    Media(HlsMedia),
+}
+
+// one of the streams in a master playlist
+// fetch it with SubsonicClient::hls_playlist to get its segments
+// the url carries the login once it's been through the client, so Debug hides it
+#[derive(Clone, PartialEq, Eq)]
+pub struct HlsVariant {
+    pub bandwidth: Option<u64>,
+    pub resolution: Option<String>,
+    pub url: Url,
+}
+
+impl std::fmt::Debug for HlsVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HlsVariant")
+            .field("bandwidth", &self.bandwidth)
+            .field("resolution", &self.resolution)
+            .field("url", &redacted(&self.url))
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlsMedia {
+    pub target_duration: Option<Duration>,
+    pub media_sequence: u64,
+    pub segments: Vec<HlsSegment>,
+    // EXT-X-ENDLIST was seen, so no more segments are coming
+    pub ended: bool,
+}
+
+// same as HlsVariant, Debug hides the login
+#[derive(Clone, PartialEq)]
+pub struct HlsSegment {
+    pub duration: Duration,
+    pub title: Option<String>,
+    pub url: Url,
+}
+
+impl std::fmt::Debug for HlsSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HlsSegment")
+            .field("duration", &self.duration)
+            .field("title", &self.title)
+            .field("url", &redacted(&self.url))
+            .finish()
+    }
+}
+
+fn redacted(url: &Url) -> Url {
+    let mut url = url.clone();
+    auth::redact_url(&mut url);
+    url
+}
+
+impl HlsPlaylist {
+    // parse a m3u8, relative urls are resolved against base (the url the playlist came from)
+    pub fn parse(text: &str, base: &Url) -> Result<Self> {
+        let mut lines = text.lines().map(|x| x.trim()).filter(|x| !x.is_empty());
+        if lines.next() != Some("#EXTM3U") {
+            return Err(Error::decode(
+                "hls.m3u8",
+                "not a m3u8 playlist, missing #EXTM3U header",
+                text,
+            ));
+        }
+
+        let mut variants = Vec::new();
+        let mut media = HlsMedia {
+            target_duration: None,
+            media_sequence: 0,
+            segments: Vec::new(),
+            ended: false,
+        };
+        // tags that describe the next uri line
+        let mut next_variant: Option<(Option<u64>, Option<String>)> = None;
+        let mut next_segment: Option<(Duration, Option<String>)> = None;
+
+        for line in lines {
+            if let Some(attrs) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+                let attrs = attributes(attrs);
+                next_variant = Some((
+                    attrs
+                        .iter()
+                        .find(|(k, _)| *k == "BANDWIDTH")
+                        .and_then(|(_, v)| v.parse().ok()),
+                    attrs
+                        .iter()
+                        .find(|(k, _)| *k == "RESOLUTION")
+                        .map(|(_, v)| v.to_string()),
+                ));
+            } else if let Some(info) = line.strip_prefix("#EXTINF:") {
+                let (duration, title) = info.split_once(',').unwrap_or((info, ""));
+                let duration = duration
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|e| Error::decode("hls.m3u8", e, line))?;
+                next_segment = Some((
+                    Duration::from_secs_f64(duration.max(0.0)),
+                    (!title.is_empty()).then(|| title.to_string()),
+                ));
+            } else if let Some(duration) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+                media.target_duration = duration.trim().parse().ok().map(Duration::from_secs);
+            } else if let Some(sequence) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+                media.media_sequence = sequence.trim().parse().unwrap_or(0);
+            } else if line == "#EXT-X-ENDLIST" {
+                media.ended = true;
+            } else if line.starts_with('#') {
+                // every other tag is something we don't use
+                continue;
+            } else {
+                // a uri, belonging to whatever tag came before it
+                let url = base
+                    .join(line)
+                    .map_err(|e| Error::decode("hls.m3u8", e, line))?;
+                if let Some((bandwidth, resolution)) = next_variant.take() {
+                    variants.push(HlsVariant {
+                        bandwidth,
+                        resolution,
+                        url,
+                    });
+                } else if let Some((duration, title)) = next_segment.take() {
+                    media.segments.push(HlsSegment {
+                        duration,
+                        title,
+                        url,
+                    });
+                }
+            }
+        }
+
+        if variants.is_empty() {
+            Ok(HlsPlaylist::Media(media))
+        } else {
+            Ok(HlsPlaylist::Master(variants))
+        }
+    }
+}
+
+// splits a attribute list, ex: PROGRAM-ID=1,BANDWIDTH=1000,CODECS="a,b"
+fn attributes(list: &str) -> Vec<(&str, &str)> {
+    let mut out = Vec::new();
+    let mut rest = list;
+    while !rest.is_empty() {
+        let (key, after) = match rest.split_once('=') {
+            Some(x) => x,
+            None => break,
+        };
+        let (value, after) = if let Some(quoted) = after.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (
+                &quoted[..end],
+                quoted[end..]
+                    .trim_start_matches('"')
+                    .trim_start_matches(','),
+            )
+        } else {
+            after.split_once(',').unwrap_or((after, ""))
+        };
+        out.push((key.trim(), value));
+        rest = after;
+    }
+    out
+}
+
+impl SubsonicClient {
+    pub async fn hls(
+        &self,
+        id: &SongId,
+        bit_rates: &[u32],
+        audio_track: Option<&str>,
+    ) -> Result<HlsPlaylist> {
+        if !server_req!(self, 1, 8) {
+            return Err(Error::APIVersionMismatch(
+                self.version,
+                ApiVersion::new(1, 8, 0),
+            ));
+        }
+
+        let mut args = vec![("id", id.to_string())];
+        // more than one bit rate makes the server send a master playlist
+        args.extend(bit_rates.iter().map(|x| ("bitRate", x.to_string())));
+        if let Some(audio_track) = audio_track {
+            if !server_req!(self, 1, 15) {
+                return Err(Error::ArgsVersionMismatch(
+                    self.version,
+                    ApiVersion::new(1, 15, 0),
+                ));
+            }
+            args.push(("audioTrack", audio_track.to_string()));
+        }
+        self.hls_playlist(&self.make_url("hls.m3u8", &args)?).await
+    }
+
+    // fetch a playlist by url, mostly for the variants of a master playlist
+    pub async fn hls_playlist(&self, url: &Url) -> Result<HlsPlaylist> {
+        self.check_auth()?;
+        let url = self.authorize_url(url.clone());
+        let text = self
+            .make_raw_req("hls.m3u8", url.clone())
+            .await?
+            .text()
+            .await
+            .map_err(|e| Error::http("hls.m3u8", e))?;
+        Ok(self.authorize(HlsPlaylist::parse(&text, &url)?))
+    }
+
+    fn authorize(&self, playlist: HlsPlaylist) -> HlsPlaylist {
+        match playlist {
+            HlsPlaylist::Master(variants) => HlsPlaylist::Master(
+                variants
+                    .into_iter()
+                    .map(|x| HlsVariant {
+                        url: self.authorize_url(x.url),
+                        ..x
+                    })
+                    .collect(),
+            ),
+            HlsPlaylist::Media(media) => HlsPlaylist::Media(HlsMedia {
+                segments: media
+                    .segments
+                    .into_iter()
+                    .map(|x| HlsSegment {
+                        url: self.authorize_url(x.url),
+                        ..x
+                    })
+                    .collect(),
+                ..media
+            }),
+        }
+    }
+
+    // the urls in a playlist point back at the server, but don't carry the login
+    // so put it on there if it's missing, urls to anywhere else are left alone
+    fn authorize_url(&self, mut url: Url) -> Url {
+        let ours = Url::parse(&self.url)
+            .map(|base| base.origin() == url.origin())
+            .unwrap_or(false);
+        let has_auth = url.query_pairs().any(|(k, _)| k == "u" || k == "apiKey");
+        if ours && !has_auth {
+            url.query_pairs_mut().extend_pairs(self.base_args());
+        }
+        url
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("http://localhost:4040/rest/hls.m3u8?id=800&u=admin").unwrap()
+    }
+
+    #[test]
+    fn master_playlist() {
+        let text = "#EXTM3U\n\
+                    #EXT-X-VERSION:1\n\
+                    #EXT-X-STREAM-INF:PROGRAM-ID=1,BANDWIDTH=1000000,CODECS=\"avc1.42e01e,mp4a.40.2\"\n\
+                    /rest/hls.m3u8?id=800&bitRate=1000\n\
+                    #EXT-X-STREAM-INF:PROGRAM-ID=1,BANDWIDTH=2000000,RESOLUTION=1280x720\n\
+                    hls.m3u8?id=800&bitRate=2000\n";
+        let variants = match HlsPlaylist::parse(text, &base()).unwrap() {
+            HlsPlaylist::Master(x) => x,
+            x => panic!("expected a master playlist, got {:?}", x),
+        };
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants[0].bandwidth, Some(1_000_000));
+        assert_eq!(variants[0].resolution, None);
+        assert_eq!(
+            variants[0].url.as_str(),
+            "http://localhost:4040/rest/hls.m3u8?id=800&bitRate=1000"
+        );
+        assert_eq!(variants[1].bandwidth, Some(2_000_000));
+        assert_eq!(variants[1].resolution.as_deref(), Some("1280x720"));
+        assert_eq!(
+            variants[1].url.as_str(),
+            "http://localhost:4040/rest/hls.m3u8?id=800&bitRate=2000"
+        );
+    }
+
+    #[test]
+    fn media_playlist() {
+        let text = "#EXTM3U\n\
+                    #EXT-X-VERSION:1\n\
+                    #EXT-X-TARGETDURATION:10\n\
+                    #EXT-X-MEDIA-SEQUENCE:3\n\
+                    #EXTINF:10,\n\
+                    /rest/stream.view?id=800&hls=true&timeOffset=0\n\
+                    #EXTINF:4.5,last one\n\
+                    http://cdn.example.com/stream.view?id=800&hls=true&timeOffset=10\n\
+                    #EXT-X-ENDLIST\n";
+        let media = match HlsPlaylist::parse(text, &base()).unwrap() {
+            HlsPlaylist::Media(x) => x,
+            x => panic!("expected a media playlist, got {:?}", x),
+        };
+        assert_eq!(media.target_duration, Some(Duration::from_secs(10)));
+        assert_eq!(media.media_sequence, 3);
+        assert!(media.ended);
+        assert_eq!(media.segments.len(), 2);
+        assert_eq!(media.segments[0].duration, Duration::from_secs(10));
+        assert_eq!(media.segments[0].title, None);
+        assert_eq!(
+            media.segments[0].url.as_str(),
+            "http://localhost:4040/rest/stream.view?id=800&hls=true&timeOffset=0"
+        );
+        assert_eq!(media.segments[1].duration, Duration::from_millis(4500));
+        assert_eq!(media.segments[1].title.as_deref(), Some("last one"));
+        assert_eq!(media.segments[1].url.host_str(), Some("cdn.example.com"));
+    }
+
+    #[test]
+    fn debug_hides_the_login() {
+        let text = "#EXTM3U\n\
+                    #EXT-X-STREAM-INF:BANDWIDTH=1000000\n\
+                    /rest/hls.m3u8?id=800&bitRate=1000\n";
+        let segments = "#EXTM3U\n#EXTINF:10,\n/rest/stream.view?id=800&hls=true\n";
+        let clients = [
+            SubsonicClient::builder("http://localhost:4040", "admin", "sesame")
+                .version(ApiVersion::new(1, 12, 0)),
+            SubsonicClient::builder("http://localhost:4040", "admin", "sesame")
+                .version(ApiVersion::new(1, 16, 1)),
+            SubsonicClient::builder("http://localhost:4040", "", "").auth(Auth::ApiKey("key123".into())),
+        ];
+        for client in clients {
+            let client = client.build().unwrap();
+            for text in [text, segments] {
+                let playlist = client.authorize(HlsPlaylist::parse(text, &base()).unwrap());
+                let url = match &playlist {
+                    HlsPlaylist::Master(x) => &x[0].url,
+                    HlsPlaylist::Media(x) => &x.segments[0].url,
+                };
+                let shown = format!("{:?}", playlist);
+                let mut secrets = 0;
+                for (k, v) in url.query_pairs() {
+                    if auth::SECRET_PARAMS.contains(&&*k) {
+                        assert!(!shown.contains(&*v), "{} in {}", v, shown);
+                        secrets += 1;
+                    }
+                }
+                assert_ne!(secrets, 0, "{}", url);
+                assert!(shown.contains("id=800"), "{}", shown);
+            }
+        }
+    }
+
+    #[test]
+    fn not_a_playlist() {
+        assert!(HlsPlaylist::parse("<subsonic-response/>", &base()).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// the ids of the things the api hands around
+// they're opaque strings (ex: navidrome's "al-123"), wrapped so one kind can't be passed as another
+macro_rules! id {
+    ($($name:ident),* $(,)?) => {$(
+        #[derive(Debug, Default, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(id: impl Into<String>) -> Self {
+                $name(id.into())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                $name(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                $name(id.to_string())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        // some servers send numeric ids as json numbers
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                crate::deserialize::string_or_number(deserializer).map($name)
+            }
+        }
+    )*};
+}
+
+id!(
+    SongId,
+    AlbumId,
+    ArtistId,
+    PlaylistId,
+    ShareId,
+    PodcastChannelId,
+    PodcastEpisodeId,
+    RadioStationId,
+    // the folder based api (getMusicDirectory), what a song's parent is
+    DirectoryId,
+);
+
+// what the folder based info endpoints (getArtistInfo, getSimilarSongs) take:
+// a folder (an artist's or an album's) or a song, they're one kind of id on the server
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FolderItemId {
+    Directory(DirectoryId),
+    Song(SongId),
+}
+
+impl FolderItemId {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FolderItemId::Directory(x) => x.as_str(),
+            FolderItemId::Song(x) => x.as_str(),
+        }
+    }
+}
+
+impl From<DirectoryId> for FolderItemId {
+    fn from(id: DirectoryId) -> Self {
+        FolderItemId::Directory(id)
+    }
+}
+
+impl From<SongId> for FolderItemId {
+    fn from(id: SongId) -> Self {
+        FolderItemId::Song(id)
+    }
+}
+
+impl std::fmt::Display for FolderItemId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::deserialize::Media;
+
+    #[test]
+    fn strings_and_numbers() {
+        // navidrome style ids and numeric ones both make it through
+        let song: Media = serde_json::from_str(
+            r#"{"id": 12, "isDir": false, "title": "x", "albumId": "al-123", "artistId": 7}"#,
+        )
+        .unwrap();
+        assert_eq!(song.id, SongId::from("12"));
+        assert_eq!(song.album_id, Some(AlbumId::from("al-123")));
+        assert_eq!(song.artist_id, Some(ArtistId::from("7")));
+
+        let song: Media = serde_xml_rs::from_str(
+            r#"<song id="tr-1" isDir="false" title="x" albumId="al-123" channelId="9"/>"#,
+        )
+        .unwrap();
+        assert_eq!(song.id.as_str(), "tr-1");
+        assert_eq!(song.album_id, Some(AlbumId::from("al-123")));
+        assert_eq!(song.artist_id, None);
+        assert_eq!(song.episode_id(), Some(PodcastEpisodeId::from("tr-1")));
+
+        // the parent goes straight back into get_music_directory
+        let song: Media =
+            serde_json::from_str(r#"{"id": "1", "isDir": false, "title": "x", "parent": 10}"#).unwrap();
+        assert_eq!(song.parent, Some(DirectoryId::from("10")));
+        assert_eq!(song.directory_id(), None);
+        assert_eq!(song.folder_item_id(), FolderItemId::Song(SongId::from("1")));
+
+        // a folder in a directory listing is a child like the songs, with a directory id
+        let folder: Media =
+            serde_json::from_str(r#"{"id": "10", "isDir": true, "title": "Arrival"}"#).unwrap();
+        assert_eq!(folder.directory_id(), Some(DirectoryId::from("10")));
+        assert_eq!(folder.folder_item_id(), FolderItemId::from(DirectoryId::from("10")));
+        assert_eq!(folder.folder_item_id().to_string(), "10");
+    }
+}
@@ -1,19 +1,39 @@
-use md5::{Digest, Md5};
-use reqwest::{Client, ClientBuilder};
-use thiserror::Error;
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+use reqwest::{Client, ClientBuilder, RequestBuilder, Url};
 
+pub mod auth;
+pub mod builder;
 pub mod deserialize;
+pub mod error;
+pub mod hls;
+pub mod id;
+#[cfg(test)]
+mod mock;
+pub mod opensubsonic;
+pub mod paginate;
 pub mod restapi;
+mod serialize;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod stream;
+pub mod timestamp;
+pub mod version;
 
-use deserialize::SubsonicResp;
-
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("the server is not new enough to use this api, server is {0}.{1}.{2} while api requested is {3}.{4}.{5}")]
-    APIVersionMismatch(u32, u32, u32, u32, u32, u32),
-    #[error("the server is not new enough to use this arg, server is {0}.{1}.{2} while arg required is {3}.{4}.{5}")]
-    ArgsVersionMismatch(u32, u32, u32, u32, u32, u32),
-}
+use deserialize::{FromSubsonicInfo, SubsonicResp};
+use auth::Salter;
+pub use auth::{Auth, Secret};
+pub use builder::SubsonicClientBuilder;
+pub use error::{Error, Result};
+pub use id::{
+    AlbumId, ArtistId, DirectoryId, FolderItemId, PlaylistId, PodcastChannelId, PodcastEpisodeId,
+    RadioStationId, ShareId, SongId,
+};
+pub use opensubsonic::{Extension, ServerInfo};
+pub use paginate::Paginator;
+#[cfg(feature = "server")]
+pub use server::{SubsonicBackend, SubsonicServer};
+pub use timestamp::Timestamp;
+pub use version::ApiVersion;
 
 #[derive(Debug)]
 pub enum CaptionType {
@@ -30,106 +50,354 @@ impl std::fmt::Display for CaptionType {
     }
 }
 
+// what the server is asked to answer in, both decode into the same types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseFormat {
+    #[default]
+    Xml,
+    Json,
+}
+
+impl ResponseFormat {
+    fn decode(self, endpoint: &'static str, body: &str) -> Result<SubsonicResp> {
+        match self {
+            ResponseFormat::Xml => {
+                SubsonicResp::from_xml(body).map_err(|e| Error::decode(endpoint, e, body))
+            }
+            ResponseFormat::Json => {
+                SubsonicResp::from_json(body).map_err(|e| Error::decode(endpoint, e, body))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ResponseFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match *self {
+            ResponseFormat::Xml => "xml",
+            ResponseFormat::Json => "json",
+        })
+    }
+}
+
+// the list types for get_album_list and get_album_list2
+// by_year and by_genre carry their required parameters so they can't be left out
+#[derive(Debug, Clone)]
+pub enum AlbumListType {
+    Random,
+    Newest,
+    Highest,
+    Frequent,
+    Recent,
+    AlphabeticalByName,
+    AlphabeticalByArtist,
+    Starred,
+    ByYear { from: u32, to: u32 },
+    ByGenre { genre: String },
+}
+
+impl AlbumListType {
+    // the server version that started accepting this type
+    fn since(&self) -> ApiVersion {
+        match *self {
+            AlbumListType::Random
+            | AlbumListType::Newest
+            | AlbumListType::Highest
+            | AlbumListType::Frequent
+            | AlbumListType::Recent => ApiVersion::new(1, 2, 0),
+            AlbumListType::AlphabeticalByName
+            | AlbumListType::AlphabeticalByArtist
+            | AlbumListType::Starred => ApiVersion::new(1, 8, 0),
+            AlbumListType::ByYear { .. } | AlbumListType::ByGenre { .. } => {
+                ApiVersion::new(1, 10, 1)
+            }
+        }
+    }
+
+    // the type specific parameters that go along with the type
+    fn args(&self) -> Vec<(&'static str, String)> {
+        match self {
+            AlbumListType::ByYear { from, to } => {
+                vec![("fromYear", from.to_string()), ("toYear", to.to_string())]
+            }
+            AlbumListType::ByGenre { genre } => vec![("genre", genre.clone())],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for AlbumListType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match *self {
+            AlbumListType::Random => "random",
+            AlbumListType::Newest => "newest",
+            AlbumListType::Highest => "highest",
+            AlbumListType::Frequent => "frequent",
+            AlbumListType::Recent => "recent",
+            AlbumListType::AlphabeticalByName => "alphabeticalByName",
+            AlbumListType::AlphabeticalByArtist => "alphabeticalByArtist",
+            AlbumListType::Starred => "starred",
+            AlbumListType::ByYear { .. } => "byYear",
+            AlbumListType::ByGenre { .. } => "byGenre",
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct SubsonicClient {
     username: String,
-    password: String,
+    auth: Auth,
+    // the server said no to tokens (error 41), so passwords go as p=enc: instead
+    token_rejected: bool,
+    salter: Salter,
+    // the c param, how the server tells apps apart
+    client_name: String,
+    user_agent: Option<String>,
+    form_post: bool,
     url: String,
     client: Client,
-    ver_major: u32,
-    ver_minor: u32,
-    ver_bugfix: u32,
+    version: ApiVersion,
+    format: ResponseFormat,
+    server: ServerInfo,
 }
 
 macro_rules! server_req {
     ($context:expr, $major:expr, $minor:expr, $bugfix:expr) => {{
-        $context.ver_major >= $major
-            && $context.ver_minor >= $minor
-            && $context.ver_bugfix >= $bugfix
+        $context.version >= ApiVersion::new($major, $minor, $bugfix)
     }};
     ($context:expr, $major:expr, $minor:expr) => {{
-        $context.ver_major >= $major && $context.ver_minor >= $minor
+        $context.version >= ApiVersion::new($major, $minor, 0)
     }};
 }
 
 pub(crate) use server_req;
 
+// like server_req, but for OpenSubsonic extensions
+macro_rules! server_ext {
+    ($context:expr, $extension:expr) => {{
+        $context.server.supports(&$extension)
+    }};
+}
+
+pub(crate) use server_ext;
+
 impl SubsonicClient {
-    pub fn new(username: String, password: String, url: String) -> anyhow::Result<Self> {
-        let raw: SubsonicResp = serde_xml_rs::from_str(
-            reqwest::blocking::get(format!("{}/rest/ping", url))?
-                .text()?
-                .as_str(),
-        )?;
-        let version: Vec<&str> = raw.version.split(".").collect();
-        let ver_major = version[0].parse::<u32>()?;
-        let ver_minor = version[1].parse::<u32>()?;
-        let ver_bugfix = version[2].parse::<u32>()?;
-        let cl = ClientBuilder::new().build()?;
-
-        Ok(SubsonicClient {
-            username,
-            password,
-            url,
-            ver_major,
-            ver_minor,
-            ver_bugfix,
-            client: cl,
+    async fn make_req(&self, endpoint: &'static str, url: Url) -> Result<SubsonicResp> {
+        let body = async { self.request(url).send().await?.error_for_status()?.text().await }
+            .await
+            .map_err(|e| Error::http(endpoint, e))?;
+        let resp = self.format.decode(endpoint, &body)?;
+        // a failed response is a error, no matter what endpoint it came from
+        match resp.failure() {
+            Some((code, message)) => Err(Error::ServerError(code, message)),
+            None => Ok(resp),
+        }
+    }
+
+    // the api version of the server, endpoints and args are checked against this
+    pub fn api_version(&self) -> ApiVersion {
+        self.version
+    }
+
+    pub fn response_format(&self) -> ResponseFormat {
+        self.format
+    }
+
+    pub fn client_name(&self) -> &str {
+        &self.client_name
+    }
+
+    // what the server said about itself when connecting
+    pub fn server_info(&self) -> &ServerInfo {
+        &self.server
+    }
+
+    // if the server advertised a OpenSubsonic extension
+    pub fn supports(&self, extension: &Extension) -> bool {
+        self.server.supports(extension)
+    }
+
+    // a request for a endpoint that returns a subsonic response
+    // the response must hold what the endpoint is supposed to return
+    async fn make_typed_req<T: FromSubsonicInfo>(
+        &self,
+        endpoint: &'static str,
+        url: Url,
+    ) -> Result<T> {
+        T::from_info(self.make_req(endpoint, url).await?.resp).ok_or(Error::UnexpectedResponse {
+            endpoint,
+            expected: std::any::type_name::<T>(),
         })
     }
 
-    fn salt_pass(&self) -> (String, String) {
-        let mut md5 = Md5::new();
-        // make salt from generating 10 random numbers and translating to hex
-        let salt: String = std::iter::repeat_with(|| fastrand::u8(..=15))
-            .take(10)
-            .fold("".to_string(), |accum, x| accum + &format!("{:x?}", x));
-
-        // hash the password and salt
-        md5.update(self.password.clone() + &salt);
-        let mut hash: [u8; 16] = Default::default();
-        hash.copy_from_slice(&md5.finalize());
-
-        // return the hash and salt
-        (
-            hash.iter()
-                .copied()
-                .fold("".to_string(), |accum, x| accum + &format!("{:x?}", x)),
-            salt,
-        )
-    }
-
-    async fn make_req(&self, url: String) -> anyhow::Result<SubsonicResp> {
-        Ok(serde_xml_rs::from_str(
-            &self.client.get(url).send().await?.text().await?,
-        )?)
-    }
-
-    pub fn make_url(&self) -> (String, String) {
-        (
-            // base url
-            self.url.clone() + "/rest/",
-            // base url parameters
-            format!(
-                // username, version, format
-                "?u={}&v={}.{}.{}&f=xml&",
-                self.username, self.ver_major, self.ver_minor, self.ver_bugfix
+    // every request goes out through here
+    // with form_post on (and the server advertising formPost) the params go in a POST body,
+    // so long lists of ids fit and the login stays out of access logs
+    fn request(&self, mut url: Url) -> RequestBuilder {
+        let req = match url.query() {
+            Some(query) if self.form_post && server_ext!(self, Extension::FormPost) => {
+                let body = query.to_string();
+                url.set_query(None);
+                self.client
+                    .post(url)
+                    .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(body)
+            }
+            _ => self.client.get(url),
+        };
+        match &self.user_agent {
+            Some(user_agent) => req.header(USER_AGENT, user_agent),
+            None => req,
+        }
+    }
+
+    // the parameters every request carries: version, client name, format and the login
+    fn base_args(&self) -> Vec<(&'static str, String)> {
+        let mut args = self.auth_args();
+        args.push(("v", self.version.to_string()));
+        args.push(("c", self.client_name.clone()));
+        args.push(("f", self.format.to_string()));
+        args
+    }
+
+    // builds the full url for a api call
+    // every value is percent encoded by the query builder, so nothing here is spliced in raw
+    pub fn make_url(&self, endpoint: &str, args: &[(&str, String)]) -> Result<Url> {
+        self.check_auth()?;
+        let mut url = Url::parse(&format!("{}/rest/{}", self.url.trim_end_matches('/'), endpoint))
+            .map_err(|e| Error::InvalidArgument(format!("bad server url {:?}: {}", self.url, e)))?;
+        url.query_pairs_mut()
+            .extend_pairs(self.base_args())
+            .extend_pairs(args);
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn client(username: &str) -> SubsonicClient {
+        SubsonicClient::builder("http://localhost:4040/", username, "sesame")
+            .version(ApiVersion::new(1, 16, 1))
+            .build()
+            .unwrap()
+    }
+
+    // pull a single query value back out of a url
+    fn query_value(url: &Url, name: &str) -> Vec<String> {
+        url.query_pairs()
+            .filter(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn offline_build() {
+        let client = client("admin");
+        assert_eq!(client.api_version(), ApiVersion::new(1, 16, 1));
+        assert!(SubsonicClient::builder("not a url", "admin", "sesame")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn reserved_characters_are_encoded() {
+        let client = client("rock+roll@home");
+        let url = client
+            .make_url(
+                "search3",
+                &[
+                    ("query", "AC/DC & friends".to_string()),
+                    ("name", "mix #1 = 100%?".to_string()),
+                ],
             )
-            // password
-            + &(if server_req!(self, 1, 13) {
-                // salted password
-                let pair = self.salt_pass();
-                format!("t={}&s={}", pair.0, pair.1)
-            } else {
-                // hex encoded password
-                format!(
-                    "p=enc:{}",
-                    self.password
-                        .clone()
-                        .chars()
-                        .fold("".to_string(), |accum, x| accum + &format!("{:x?}", x))
-                )
-            }),
-        )
+            .unwrap();
+
+        assert_eq!(url.path(), "/rest/search3");
+        let raw = url.query().unwrap();
+        assert!(raw.contains("u=rock%2Broll%40home"));
+        assert!(raw.contains("query=AC%2FDC+%26+friends"));
+        assert!(raw.contains("name=mix+%231+%3D+100%25%3F"));
+        assert!(url.fragment().is_none());
+
+        assert_eq!(query_value(&url, "u"), ["rock+roll@home"]);
+        assert_eq!(query_value(&url, "query"), ["AC/DC & friends"]);
+        assert_eq!(query_value(&url, "name"), ["mix #1 = 100%?"]);
+    }
+
+    #[test]
+    fn non_ascii_is_encoded() {
+        let client = client("björk");
+        let url = client
+            .make_url(
+                "addChatMessage",
+                &[("message", "Sigur Rós — Ágætis byrjun 🎵".to_string())],
+            )
+            .unwrap();
+
+        assert!(url.query().unwrap().is_ascii());
+        assert!(url.query().unwrap().contains("u=bj%C3%B6rk"));
+        assert_eq!(query_value(&url, "u"), ["björk"]);
+        assert_eq!(
+            query_value(&url, "message"),
+            ["Sigur Rós — Ágætis byrjun 🎵"]
+        );
+    }
+
+    #[test]
+    fn repeated_args_are_kept() {
+        let client = client("admin");
+        let url = client
+            .make_url(
+                "createPlaylist",
+                &[
+                    ("songId", "a&b".to_string()),
+                    ("songId", "c d".to_string()),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(query_value(&url, "songId"), ["a&b", "c d"]);
+        assert_eq!(query_value(&url, "t").len(), 1);
+        assert_eq!(query_value(&url, "s").len(), 1);
+    }
+
+    #[test]
+    fn auth_modes() {
+        let with = |auth: Auth, version: ApiVersion| {
+            let client = SubsonicClient::builder("http://localhost:4040/", "admin", "")
+                .auth(auth)
+                .version(version)
+                .build()
+                .unwrap();
+            client.make_url("ping", &[]).unwrap()
+        };
+
+        // old servers get the password hex encoded, byte by byte
+        let url = with(Auth::Password("sesame".into()), ApiVersion::new(1, 12, 0));
+        assert_eq!(query_value(&url, "p"), ["enc:736573616d65"]);
+        assert!(query_value(&url, "t").is_empty());
+        let url = with(Auth::Password("pä".into()), ApiVersion::new(1, 12, 0));
+        assert_eq!(query_value(&url, "p"), ["enc:70c3a4"]);
+
+        let url = with(
+            Auth::Token {
+                token: "26719a1196d2a940705a59634eb18eab".into(),
+                salt: "c19b2d".to_string(),
+            },
+            ApiVersion::new(1, 16, 1),
+        );
+        assert_eq!(query_value(&url, "u"), ["admin"]);
+        assert_eq!(query_value(&url, "t"), ["26719a1196d2a940705a59634eb18eab"]);
+        assert_eq!(query_value(&url, "s"), ["c19b2d"]);
+        assert!(query_value(&url, "p").is_empty());
+
+        // a key replaces the username too, sending both is a error
+        let url = with(Auth::ApiKey("key123".into()), ApiVersion::new(1, 16, 1));
+        assert_eq!(query_value(&url, "apiKey"), ["key123"]);
+        for name in ["u", "p", "t", "s"] {
+            assert!(query_value(&url, name).is_empty(), "{} was sent", name);
+        }
     }
 }
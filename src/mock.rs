@@ -0,0 +1,153 @@
+// a tiny http server for tests, answers every request with whatever the handler returns
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    // path and query, ex: /rest/ping?u=admin
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn has_query(&self, name: &str) -> bool {
+        self.query(name).is_some()
+    }
+
+    pub fn query(&self, name: &str) -> Option<String> {
+        let url = reqwest::Url::parse(&format!("http://localhost{}", self.target)).ok()?;
+        let value = url
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned());
+        value
+    }
+
+    // every value of a param in a form encoded body
+    pub fn form(&self, name: &str) -> Vec<String> {
+        let url = match reqwest::Url::parse(&format!("http://localhost/?{}", self.body)) {
+            Ok(x) => x,
+            Err(_) => return Vec::new(),
+        };
+        let values = url
+            .query_pairs()
+            .filter(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+            .collect();
+        values
+    }
+}
+
+pub struct MockServer {
+    pub url: String,
+    pub requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    // the handler gives back (content type, body)
+    pub fn start<F>(handler: F) -> MockServer
+    where
+        F: Fn(&Request) -> (&'static str, String) + Send + 'static,
+    {
+        MockServer::start_with_status(move |req| {
+            let (content_type, body) = handler(req);
+            (200, content_type, body)
+        })
+    }
+
+    // same as start, with the http status first, ex: (503, "text/html", ...)
+    pub fn start_with_status<F>(handler: F) -> MockServer
+    where
+        F: Fn(&Request) -> (u16, &'static str, String) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(x) => x,
+                    Err(_) => break,
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let target = parts.next().unwrap_or_default().to_string();
+
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((k, v)) = line.split_once(':') {
+                        headers.push((k.trim().to_string(), v.trim().to_string()));
+                    }
+                }
+                let len = headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, v)| v.parse::<usize>().ok())
+                    .unwrap_or(0);
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+
+                let request = Request {
+                    method,
+                    target,
+                    headers,
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                };
+                let (status, content_type, body) = handler(&request);
+                seen.lock().unwrap().push(request);
+
+                // the reason phrase is only for show, clients go by the number
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    if status == 200 { "OK" } else { "Status" },
+                    content_type,
+                    body.len(),
+                    body
+                );
+            }
+        });
+
+        MockServer { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+// a subsonic response with the given status, version and inner xml
+pub fn xml(status: &str, version: &str, inner: &str) -> (&'static str, String) {
+    (
+        "text/xml; charset=utf-8",
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><subsonic-response xmlns="http://subsonic.org/restapi" status="{}" version="{}">{}</subsonic-response>"#,
+            status, version, inner
+        ),
+    )
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+// the OpenSubsonic extensions a server can advertise through getOpenSubsonicExtensions
+// https://opensubsonic.netlify.app/docs/extensions/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Extension {
+    ApiKeyAuthentication,
+    FormPost,
+    GetPodcastEpisode,
+    IndexBasedQueue,
+    SongLyrics,
+    Transcoding,
+    TranscodeOffset,
+    // anything this crate doesn't know about yet, by name
+    Other(String),
+}
+
+impl Extension {
+    pub fn name(&self) -> &str {
+        match self {
+            Extension::ApiKeyAuthentication => "apiKeyAuthentication",
+            Extension::FormPost => "formPost",
+            Extension::GetPodcastEpisode => "getPodcastEpisode",
+            Extension::IndexBasedQueue => "indexBasedQueue",
+            Extension::SongLyrics => "songLyrics",
+            Extension::Transcoding => "transcoding",
+            Extension::TranscodeOffset => "transcodeOffset",
+            Extension::Other(x) => x,
+        }
+    }
+}
+
+impl From<&str> for Extension {
+    fn from(name: &str) -> Self {
+        match name {
+            "apiKeyAuthentication" => Extension::ApiKeyAuthentication,
+            "formPost" => Extension::FormPost,
+            "getPodcastEpisode" => Extension::GetPodcastEpisode,
+            "indexBasedQueue" => Extension::IndexBasedQueue,
+            "songLyrics" => Extension::SongLyrics,
+            "transcoding" => Extension::Transcoding,
+            "transcodeOffset" => Extension::TranscodeOffset,
+            x => Extension::Other(x.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Extension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// what a server said about itself, every response carries this
+// a plain subsonic server only fills in the api version, which the client keeps separately
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerInfo {
+    // the server software, ex: navidrome
+    pub kind: Option<String>,
+    // the version of the server software (not the api)
+    pub version: Option<String>,
+    pub open_subsonic: bool,
+    // the advertised extensions, along with the versions of each the server implements
+    pub extensions: HashMap<Extension, Vec<u32>>,
+}
+
+impl ServerInfo {
+    pub fn supports(&self, extension: &Extension) -> bool {
+        self.extensions.contains_key(extension)
+    }
+
+    pub fn extension_versions(&self, extension: &Extension) -> Option<&[u32]> {
+        self.extensions.get(extension).map(|x| x.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for name in [
+            "apiKeyAuthentication",
+            "formPost",
+            "getPodcastEpisode",
+            "indexBasedQueue",
+            "songLyrics",
+            "transcoding",
+            "transcodeOffset",
+            "somethingNew",
+        ] {
+            assert_eq!(Extension::from(name).name(), name);
+        }
+        assert_eq!(
+            Extension::from("somethingNew"),
+            Extension::Other("somethingNew".to_string())
+        );
+    }
+}
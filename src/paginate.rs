@@ -0,0 +1,373 @@
+use super::*;
+use deserialize::{Album, Artist, Media};
+use futures::future::{ready, BoxFuture};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+// the most a server hands back in one page, asking for more just gets a page this long
+// since a short page is what ends a stream, page sizes are kept at or under it
+pub const MAX_PAGE_SIZE: u32 = 500;
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+// a random album list never comes back short, so without max_items it stops after this many
+pub const RANDOM_MAX_ITEMS: usize = 500;
+
+// gets one page, given (offset, count, music folder id)
+type Fetch<'a, T> = Box<dyn Fn(u32, u32, Option<String>) -> BoxFuture<'a, Result<Vec<T>>> + Send + 'a>;
+
+// every item of a paged endpoint, one request per page
+// pages are asked for until one comes back short (or max_items is reached)
+// the settings are read when the stream is first polled, ex:
+// client.search3_songs("abba").page_size(500).max_items(2000).try_collect::<Vec<_>>().await
+// a error ends the stream after it's handed on
+pub struct Paginator<'a, T> {
+    fetch: Option<Fetch<'a, T>>,
+    page_size: u32,
+    max_items: Option<usize>,
+    prefetch: usize,
+    music_folder_id: Option<String>,
+    items: Option<BoxStream<'a, Result<T>>>,
+}
+
+impl<'a, T: Send + 'a> Paginator<'a, T> {
+    fn new(fetch: Fetch<'a, T>) -> Self {
+        Paginator {
+            fetch: Some(fetch),
+            page_size: DEFAULT_PAGE_SIZE,
+            max_items: None,
+            prefetch: 0,
+            music_folder_id: None,
+            items: None,
+        }
+    }
+
+    // clamped to 1..=MAX_PAGE_SIZE
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    // stop after this many items, no more pages than needed are asked for
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    // how many pages are asked for ahead of the one being read, 0 (the default) is one at a time
+    // the pages asked for past the end come back empty and are thrown away
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.prefetch = pages;
+        self
+    }
+
+    pub fn music_folder_id(mut self, music_folder_id: &str) -> Self {
+        self.music_folder_id = Some(music_folder_id.to_string());
+        self
+    }
+
+    fn start(&mut self) -> BoxStream<'a, Result<T>> {
+        let fetch = match self.fetch.take() {
+            Some(x) => x,
+            None => return stream::empty().boxed(),
+        };
+        let page_size = self.page_size;
+        let max_items = self.max_items.unwrap_or(usize::MAX);
+        let music_folder_id = self.music_folder_id.clone();
+
+        // set by the page that's short (or failed), it's the last one
+        // no more pages are asked for after it, and the ones already asked for are dropped
+        let done = Arc::new(AtomicBool::new(false));
+        let last = done.clone();
+        let offsets = (0..)
+            .map_while(move |page: u32| page.checked_mul(page_size))
+            .take_while(move |offset| (*offset as usize) < max_items && !last.load(Ordering::Relaxed));
+        stream::iter(offsets)
+            .map(move |offset| fetch(offset, page_size, music_folder_id.clone()))
+            .buffered(self.prefetch + 1)
+            .scan((), move |_, page| {
+                if done.load(Ordering::Relaxed) {
+                    return ready(None);
+                }
+                if !matches!(&page, Ok(items) if items.len() == page_size as usize) {
+                    done.store(true, Ordering::Relaxed);
+                }
+                ready(Some(page))
+            })
+            .flat_map(|page| match page {
+                Ok(items) => stream::iter(items.into_iter().map(Ok)).left_stream(),
+                Err(e) => stream::once(ready(Err(e))).right_stream(),
+            })
+            .take(max_items)
+            .boxed()
+    }
+}
+
+impl<'a, T: Send + 'a> Stream for Paginator<'a, T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.items.is_none() {
+            this.items = Some(this.start());
+        }
+        this.items.as_mut().unwrap().poll_next_unpin(cx)
+    }
+}
+
+impl<T> std::fmt::Debug for Paginator<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Paginator")
+            .field("page_size", &self.page_size)
+            .field("max_items", &self.max_items)
+            .field("prefetch", &self.prefetch)
+            .field("music_folder_id", &self.music_folder_id)
+            .finish_non_exhaustive()
+    }
+}
+
+// search2 and search3 page the artists, albums and songs separately
+// the other two counts are set to 0 so only the one being paged comes back
+macro_rules! search_pages {
+    ($($name:ident: $search:ident -> $item:ident ($field:ident) at $slot:literal),* $(,)?) => {$(
+        pub fn $name(&self, query: &str) -> Paginator<'_, $item> {
+            let query = query.to_string();
+            Paginator::new(Box::new(move |offset, count, music_folder_id| {
+                let query = query.clone();
+                Box::pin(async move {
+                    let mut counts = [Some(0), None, Some(0), None, Some(0), None];
+                    counts[$slot] = Some(count);
+                    counts[$slot + 1] = Some(offset);
+                    let found = self
+                        .$search(
+                            &query,
+                            counts[0],
+                            counts[1],
+                            counts[2],
+                            counts[3],
+                            counts[4],
+                            counts[5],
+                            music_folder_id.as_deref(),
+                        )
+                        .await?;
+                    Ok(found.$field)
+                })
+            }))
+        }
+    )*};
+}
+
+impl SubsonicClient {
+    search_pages!(
+        search2_artists: search2 -> Artist (artists) at 0,
+        search2_albums: search2 -> Album (albums) at 2,
+        search2_songs: search2 -> Media (songs) at 4,
+        search3_artists: search3 -> Artist (artists) at 0,
+        search3_albums: search3 -> Album (albums) at 2,
+        search3_songs: search3 -> Media (songs) at 4,
+    );
+
+    pub fn songs_by_genre(&self, genre: &str) -> Paginator<'_, Media> {
+        let genre = genre.to_string();
+        Paginator::new(Box::new(move |offset, count, music_folder_id| {
+            let genre = genre.clone();
+            Box::pin(async move {
+                self.get_songs_by_genre(&genre, Some(count), Some(offset), music_folder_id.as_deref())
+                    .await
+            })
+        }))
+    }
+
+    // a random list never comes back short, so it ends at RANDOM_MAX_ITEMS unless max_items is set
+    pub fn album_list(&self, list_type: AlbumListType) -> Paginator<'_, Album> {
+        let random = matches!(list_type, AlbumListType::Random);
+        let pages = Paginator::new(Box::new(move |offset, count, music_folder_id| {
+            let list_type = list_type.clone();
+            Box::pin(async move {
+                self.get_album_list(list_type, Some(count), Some(offset), music_folder_id.as_deref())
+                    .await
+            })
+        }));
+        if random {
+            pages.max_items(RANDOM_MAX_ITEMS)
+        } else {
+            pages
+        }
+    }
+
+    // same as album_list, RANDOM_MAX_ITEMS included
+    pub fn album_list2(&self, list_type: AlbumListType) -> Paginator<'_, Album> {
+        let random = matches!(list_type, AlbumListType::Random);
+        let pages = Paginator::new(Box::new(move |offset, count, music_folder_id| {
+            let list_type = list_type.clone();
+            Box::pin(async move {
+                self.get_album_list2(list_type, Some(count), Some(offset), music_folder_id.as_deref())
+                    .await
+            })
+        }));
+        if random {
+            pages.max_items(RANDOM_MAX_ITEMS)
+        } else {
+            pages
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::{xml, MockServer};
+    use futures::TryStreamExt;
+
+    // a server with `total` songs, answering search3 and getSongsByGenre a page at a time
+    fn library(total: u32) -> MockServer {
+        MockServer::start(move |req| {
+            let arg = |name: &str| req.query(name).and_then(|x| x.parse::<u32>().ok());
+            let (tag, count, offset) = if req.target.starts_with("/rest/search3") {
+                assert_eq!(arg("artistCount"), Some(0));
+                assert_eq!(arg("albumCount"), Some(0));
+                ("searchResult3", arg("songCount"), arg("songOffset"))
+            } else if req.target.starts_with("/rest/getSongsByGenre") {
+                ("songsByGenre", arg("count"), arg("offset"))
+            } else {
+                return xml("ok", "1.16.1", "");
+            };
+            let (count, offset) = (count.unwrap(), offset.unwrap());
+            let songs: String = (offset..total.min(offset + count))
+                .map(|x| format!(r#"<song id="{0}" isDir="false" title="song {0}"/>"#, x))
+                .collect();
+            xml("ok", "1.16.1", &format!("<{0}>{1}</{0}>", tag, songs))
+        })
+    }
+
+    fn offsets(server: &MockServer, name: &str) -> Vec<u32> {
+        server
+            .requests()
+            .iter()
+            .filter_map(|x| x.query(name))
+            .map(|x| x.parse().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn pages_until_a_short_one() {
+        let server = library(25);
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+
+        let songs: Vec<Media> = client
+            .search3_songs("song")
+            .page_size(10)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(songs.len(), 25);
+        assert_eq!(songs[24].title, "song 24");
+        assert_eq!(offsets(&server, "songOffset"), [0, 10, 20]);
+
+        // a full last page takes one more (empty) page to notice the end
+        let songs: Vec<Media> = client
+            .songs_by_genre("Rock")
+            .page_size(5)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(songs.len(), 25);
+        assert_eq!(offsets(&server, "offset"), [0, 5, 10, 15, 20, 25]);
+    }
+
+    #[tokio::test]
+    async fn max_items_and_prefetch() {
+        let server = library(1000);
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+
+        let songs: Vec<Media> = client
+            .songs_by_genre("Rock")
+            .page_size(10)
+            .max_items(35)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(songs.len(), 35);
+        assert_eq!(songs[34].id, SongId::from("34"));
+        assert_eq!(offsets(&server, "offset"), [0, 10, 20, 30]);
+
+        // oversized pages are clamped to what a server will send
+        let songs: Vec<Media> = client
+            .search3_songs("song")
+            .page_size(10_000)
+            .prefetch(2)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(songs.len(), 1000);
+        let mut asked = offsets(&server, "songOffset");
+        asked.sort_unstable();
+        // the short page at 500 ends it, and at most two pages were asked for past it
+        assert_eq!(asked[..3], [0, 500, 1000]);
+        assert!(asked.len() <= 5);
+    }
+
+    #[tokio::test]
+    async fn random_album_lists_end() {
+        // every page is full, however far in
+        let server = MockServer::start(|req| {
+            let (tag, size) = if req.target.starts_with("/rest/getAlbumList2") {
+                ("albumList2", req.query("size"))
+            } else if req.target.starts_with("/rest/getAlbumList") {
+                ("albumList", req.query("size"))
+            } else {
+                return xml("ok", "1.16.1", "");
+            };
+            let albums: String = (0..size.unwrap().parse::<u32>().unwrap())
+                .map(|x| format!(r#"<album id="{0}" name="album {0}"/>"#, x))
+                .collect();
+            xml("ok", "1.16.1", &format!("<{0}>{1}</{0}>", tag, albums))
+        });
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+
+        let albums: Vec<Album> = client
+            .album_list2(AlbumListType::Random)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(albums.len(), RANDOM_MAX_ITEMS);
+
+        let albums: Vec<Album> = client
+            .album_list(AlbumListType::Random)
+            .page_size(20)
+            .max_items(50)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(albums.len(), 50);
+        assert_eq!(offsets(&server, "offset"), [0, 100, 200, 300, 400, 0, 20, 40]);
+    }
+
+    #[tokio::test]
+    async fn errors_end_the_stream() {
+        let server = MockServer::start(|req| {
+            if req.target.starts_with("/rest/getSongsByGenre") && req.query("offset").as_deref() == Some("2") {
+                xml("failed", "1.16.1", r#"<error code="0" message="oops"/>"#)
+            } else if req.target.starts_with("/rest/getSongsByGenre") {
+                xml("ok", "1.16.1", r#"<songsByGenre><song id="1" isDir="false" title="a"/><song id="2" isDir="false" title="b"/></songsByGenre>"#)
+            } else {
+                xml("ok", "1.16.1", "")
+            }
+        });
+        let client = SubsonicClient::connect(&server.url, "admin", "sesame")
+            .await
+            .unwrap();
+
+        let results: Vec<Result<Media>> = client.songs_by_genre("Rock").page_size(2).collect().await;
+        assert_eq!(results.len(), 3);
+        assert!(results[..2].iter().all(|x| x.is_ok()));
+        assert!(matches!(results[2], Err(Error::ServerError(..))));
+    }
+}
@@ -1,16 +1,50 @@
 use super::*;
 use const_format::{map_ascii_case, Case};
-use deserialize::SubsonicResp;
+use deserialize::*;
+use id::*;
+use stream::{MediaStream, VideoSize};
 
 macro_rules! api {
-    ( $name:ident $maj:literal $min:literal $bug:literal
+    // a row of ENDPOINTS, see endpoints!
+    ( @entry @bytes $name:ident $maj:literal $min:literal $bug:literal
       $($pname:ident $amaj:literal $amin:literal $abug:literal : $nept:ty),* ) => {
-        pub async fn $name(&self, $($pname:$nept,)*) -> anyhow::Result<SubsonicResp> {
+        api!(@entry $name $maj $min $bug -> MediaStream $(, $pname $amaj $amin $abug : $nept)*)
+    };
+    ( @entry $([$ext:ident])? $name:ident $maj:literal $min:literal $bug:literal -> $ret:ty
+      $(, $pname:ident $amaj:literal $amin:literal $abug:literal : $nept:ty)* ) => {
+        Endpoint {
+            name: map_ascii_case!(Case::Camel, stringify!($name)),
+            since: ApiVersion::new($maj, $min, $bug),
+            extension: api!(@ext $($ext)?),
+            args: &[$(
+                (map_ascii_case!(Case::Camel, stringify!($pname)), ApiVersion::new($amaj, $amin, $abug))
+            ),*],
+        }
+    };
+    (@ext) => { None };
+    (@ext $ext:ident) => { Some(Extension::$ext) };
+    // binary endpoints, these hand back the body instead of parsing it
+    ( @bytes $name:ident $maj:literal $min:literal $bug:literal
+      $($pname:ident $amaj:literal $amin:literal $abug:literal : $nept:ty),* ) => {
+        api!(@gen make_stream_req -> MediaStream; $name $maj $min $bug
+             $($pname $amaj $amin $abug : $nept),*);
+    };
+    // everything else, the response is checked to be what the endpoint returns
+    // the extension in brackets has to be advertised by the server (OpenSubsonic)
+    ( $([$ext:ident])? $name:ident $maj:literal $min:literal $bug:literal -> $ret:ty
+      $(, $pname:ident $amaj:literal $amin:literal $abug:literal : $nept:ty)* ) => {
+        api!(@gen make_typed_req -> $ret; $([$ext])? $name $maj $min $bug
+             $($pname $amaj $amin $abug : $nept),*);
+    };
+    ( @gen $req:ident -> $ret:ty; $([$ext:ident])? $name:ident $maj:literal $min:literal $bug:literal
+      $($pname:ident $amaj:literal $amin:literal $abug:literal : $nept:ty),* ) => {
+        #[allow(clippy::too_many_arguments)]
+        pub async fn $name(&self, $($pname:$nept,)*) -> Result<$ret> {
+            $(if !server_ext!(self, Extension::$ext) {
+                return Err(Error::ExtensionNotSupported(Extension::$ext));
+            })?
             // server apis are version specifc
-            #[allow(unused_comparisons)]
             if server_req!(self, $maj, $min, $bug) {
-                let base = self.make_url();
-
                 // rest api call name
                 // it's always a camelcase version of the function name
                 // ex: get_genres -> getGenre
@@ -18,158 +52,246 @@ macro_rules! api {
                 const NAME: &'static str = map_ascii_case!(Case::Camel, stringify!($name));
 
                 // args (re)construction
-                // turn args into the parseable part of the url
+                // turn args into name value pairs, the url builder encodes them
                 #[allow(unused_mut)]
-                let mut args = String::new();
+                let mut args: Vec<(&str, String)> = Vec::new();
                 // for each arg
                 $({
-                    // turn into a Some() if the value isnt a Option
-                    let param = IntoOptionHelper($pname).into_option();
-                    if let Some(exist) = param {
-                        #[allow(unused_comparisons)]
+                    // an arg can turn into zero (None, empty slice), one, or many values
+                    let values = ParamValues::param_values(&$pname);
+                    if !values.is_empty() {
                         if server_req!(self, $amaj, $amin, $abug) {
-                            // push the arg onto the url
-                            args.push_str("&");
-                            args.push_str(map_ascii_case!(Case::Camel, stringify!($pname)));
-                            args.push_str("=");
-                            args.push_str(&format!("{}", exist));
+                            // push the arg onto the url, once for every value
+                            for value in values {
+                                args.push((map_ascii_case!(Case::Camel, stringify!($pname)), value));
+                            }
                         } else {
                             // note that args are *also* version specific
-                            return Err(anyhow::Error::new(Error::ArgsVersionMismatch(
-                                self.ver_major,
-                                self.ver_minor,
-                                self.ver_bugfix,
-                                $amaj,
-                                $amin,
-                                $abug,
-                            )));
+                            return Err(Error::ArgsVersionMismatch(
+                                self.version,
+                                ApiVersion::new($amaj, $amin, $abug),
+                            ));
                         }
                     }
                 })*
                 // and then make the request
-                Ok(self.make_req(base.0 + NAME + &base.1 + &args).await?)
+                self.$req(NAME, self.make_url(NAME, &args)?).await
             } else {
                 // you mismatched the api to your connected server
-                Err(anyhow::Error::new(Error::APIVersionMismatch(
-                    self.ver_major,
-                    self.ver_minor,
-                    self.ver_bugfix,
-                    $maj,
-                    $min,
-                    $bug,
-                )))
+                Err(Error::APIVersionMismatch(
+                    self.version,
+                    ApiVersion::new($maj, $min, $bug),
+                ))
             }
         }
     };
 }
 
+// every api! endpoint, this makes the client methods and ENDPOINTS out of the same list
+// so the versions the client checks its server against are the ones a server checks its clients against
+macro_rules! endpoints {
+    ($(api!($($api:tt)*);)*) => {
+        impl SubsonicClient {
+            $(api!($($api)*);)*
+        }
+
+        // the endpoints made by api!, by the name used on the url
+        pub const ENDPOINTS: &[Endpoint] = &[$(api!(@entry $($api)*)),*];
+    };
+}
+
+// when a endpoint was added to the api, and when each of its args was
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    // ex: getAlbum
+    pub name: &'static str,
+    pub since: ApiVersion,
+    // the OpenSubsonic extension the server has to advertise for it
+    pub extension: Option<Extension>,
+    pub args: &'static [(&'static str, ApiVersion)],
+}
+
+impl Endpoint {
+    // the version a arg showed up in, None for a arg the endpoint doesn't take
+    pub fn arg_since(&self, name: &str) -> Option<ApiVersion> {
+        self.args.iter().find(|(x, _)| *x == name).map(|(_, since)| *since)
+    }
+}
+
+pub const GET_ALBUM_LIST: Endpoint = Endpoint {
+    name: "getAlbumList",
+    since: ApiVersion::new(1, 2, 0),
+    extension: None,
+    args: &[
+        ("type", ApiVersion::new(1, 2, 0)),
+        ("size", ApiVersion::new(1, 2, 0)),
+        ("offset", ApiVersion::new(1, 2, 0)),
+        ("fromYear", ApiVersion::new(1, 10, 1)),
+        ("toYear", ApiVersion::new(1, 10, 1)),
+        ("genre", ApiVersion::new(1, 10, 1)),
+        ("musicFolderId", ApiVersion::new(1, 11, 0)),
+    ],
+};
+
+pub const GET_ALBUM_LIST2: Endpoint = Endpoint {
+    name: "getAlbumList2",
+    since: ApiVersion::new(1, 8, 0),
+    ..GET_ALBUM_LIST
+};
+
+pub const HLS: Endpoint = Endpoint {
+    name: "hls.m3u8",
+    since: ApiVersion::new(1, 8, 0),
+    extension: None,
+    args: &[
+        ("id", ApiVersion::new(1, 8, 0)),
+        ("bitRate", ApiVersion::new(1, 8, 0)),
+        ("audioTrack", ApiVersion::new(1, 15, 0)),
+    ],
+};
+
+// the endpoints written out by hand instead of with api!
+const HAND_WRITTEN: [&Endpoint; 3] = [&GET_ALBUM_LIST, &GET_ALBUM_LIST2, &HLS];
+
+// looks up a endpoint by the name used on the url, ex: getAlbum
+pub fn endpoint(name: &str) -> Option<&'static Endpoint> {
+    ENDPOINTS
+        .iter()
+        .chain(HAND_WRITTEN)
+        .find(|x| x.name == name)
+}
+
 // thanks Kestrer#9695 !
-// this reliably transfers something into a option if needed
-// used for constructing params
-struct IntoOptionHelper<T>(T);
+// this grew out of their IntoOptionHelper, which put a arg into a option if it wasn't one already
+// turns an arg into the values that get put on the url
+// a Option gives zero or one values, a slice gives one value per element
+// this is how the repeatable params (star, songId, etc) are made
+trait ParamValues {
+    fn param_values(&self) -> Vec<String>;
+}
 
-impl<T> IntoOptionHelper<Option<T>> {
-    fn into_option(self) -> Option<T> {
-        self.0
+macro_rules! param_values_display {
+    ($($t:ty),*) => {
+        $(impl ParamValues for $t {
+            fn param_values(&self) -> Vec<String> {
+                vec![self.to_string()]
+            }
+        })*
+    };
+}
+
+param_values_display!(&str, u32, u64, bool, CaptionType, VideoSize);
+param_values_display!(
+    SongId, &SongId, AlbumId, &AlbumId, ArtistId, &ArtistId, PlaylistId, &PlaylistId,
+    ShareId, &ShareId, PodcastChannelId, &PodcastChannelId, PodcastEpisodeId, &PodcastEpisodeId,
+    RadioStationId, &RadioStationId, DirectoryId, &DirectoryId, FolderItemId, &FolderItemId
+);
+
+// the api takes times as milliseconds since the epoch
+impl ParamValues for Timestamp {
+    fn param_values(&self) -> Vec<String> {
+        vec![self.as_millis().to_string()]
     }
 }
 
-trait IntoOptionFallback<T> {
-    fn into_option(self) -> Option<T>;
+impl<T: ParamValues> ParamValues for Option<T> {
+    fn param_values(&self) -> Vec<String> {
+        self.as_ref().map(T::param_values).unwrap_or_default()
+    }
 }
 
-impl<T> IntoOptionFallback<T> for IntoOptionHelper<T> {
-    fn into_option(self) -> Option<T> {
-        Some(self.0)
+impl<T: ParamValues> ParamValues for &[T] {
+    fn param_values(&self) -> Vec<String> {
+        self.iter().flat_map(T::param_values).collect()
     }
 }
 
-impl SubsonicClient {
-    api!(ping 1 0 0);
-    api!(get_license 1 0 0);
-    api!(get_music_folders 1 0 0);
-    api!(get_indexes 1 0 0
+endpoints! {
+    api!(ping 1 0 0 -> ());
+    api!(get_license 1 0 0 -> License);
+    api!(get_music_folders 1 0 0 -> Vec<MusicFolder>);
+    api!(get_indexes 1 0 0 -> Indexes,
         music_folder_id   1 0 0 : Option<&str>,
-        if_modified_since 1 0 0 : Option<u64>
+        if_modified_since 1 0 0 : Option<Timestamp>
     );
-    api!(get_music_directory 1 0 0
-        id 1 0 0 : &str
+    api!(get_music_directory 1 0 0 -> Directory,
+        id 1 0 0 : &DirectoryId
     );
-    api!(get_genres 1 9 0);
-    api!(get_artists 1 8 0
+    api!(get_genres 1 9 0 -> Vec<Genre>);
+    api!(get_artists 1 8 0 -> Artists,
         music_folder_id 1 8 0 : Option<&str>
     );
-    api!(get_artist 1 8 0
-        id 1 8 0 : &str
+    api!(get_artist 1 8 0 -> Artist,
+        id 1 8 0 : &ArtistId
     );
-    api!(get_album 1 8 0
-        id 1 8 0 : &str
+    api!(get_album 1 8 0 -> Album,
+        id 1 8 0 : &AlbumId
     );
-    api!(get_song 1 8 0
-        id 1 8 0 : &str
+    api!(get_song 1 8 0 -> Media,
+        id 1 8 0 : &SongId
     );
-    api!(get_videos 1 8 0);
-    api!(get_video_info 1 14 0
-        id 1 14 0 : &str
+    api!(get_videos 1 8 0 -> Vec<Media>);
+    api!(get_video_info 1 14 0 -> VideoInfo,
+        id 1 14 0 : &SongId
     );
-    api!(get_artist_info 1 11 0
-        id                  1 11 0 : &str,
+    api!(get_artist_info 1 11 0 -> PublicInfo,
+        id                  1 11 0 : &FolderItemId,
         count               1 11 0 : Option<u32>,
         include_not_present 1 11 0 : Option<bool>
     );
-    api!(get_artist_info2 1 11 0
-        id                  1 11 0 : &str,
+    api!(get_artist_info2 1 11 0 -> PublicInfo,
+        id                  1 11 0 : &ArtistId,
         count               1 11 0 : Option<u32>,
         include_not_present 1 11 0 : Option<bool>
     );
-    api!(get_album_info 1 14 0
-        id 1 14 0 : &str
+    api!(get_album_info 1 14 0 -> PublicInfo,
+        id 1 14 0 : &DirectoryId
     );
-    api!(get_album_info2 1 14 0
-        id 1 14 0 : &str
+    api!(get_album_info2 1 14 0 -> PublicInfo,
+        id 1 14 0 : &AlbumId
     );
-    api!(get_similar_songs 1 11 0
-        id    1 11 0 : &str,
+    api!(get_similar_songs 1 11 0 -> Vec<Media>,
+        id    1 11 0 : &FolderItemId,
         count 1 11 0 : Option<u32>
     );
-    api!(get_similar_songs2 1 11 0
-        id    1 11 0 : &str,
+    api!(get_similar_songs2 1 11 0 -> Vec<Media>,
+        id    1 11 0 : &ArtistId,
         count 1 11 0 : Option<u32>
     );
-    api!(get_top_songs 1 13 0
+    api!(get_top_songs 1 13 0 -> Vec<Media>,
         artist 1 13 0 : &str,
         count  1 13 0 : Option<u32>
     );
-    api!(get_random_songs 1 2 0
+    api!(get_random_songs 1 2 0 -> Vec<Media>,
         size            1 2 0 : Option<u32>,
         genre           1 2 0 : Option<&str>,
         from_year       1 2 0 : Option<u32>,
         to_year         1 2 0 : Option<u32>,
         music_folder_id 1 2 0 : Option<&str>
     );
-    api!(get_songs_by_genre 1 9 0
+    api!(get_songs_by_genre 1 9 0 -> Vec<Media>,
         genre            1 9 0 : &str,
         count            1 9 0 : Option<u32>,
         offset           1 9 0 : Option<u32>,
         music_folder_id 1 12 0 : Option<&str>
     );
-    api!(get_now_playing 1 0 0);
-    api!(get_starred 1 8 0
+    api!(get_now_playing 1 0 0 -> Vec<Media>);
+    api!(get_starred 1 8 0 -> Starred,
         music_folder_id 1 12 0 : Option<&str>
     );
-    api!(get_starred2 1 8 0
+    api!(get_starred2 1 8 0 -> Starred,
         music_folder_id 1 12 0 : Option<&str>
     );
-    api!(search 1 0 0
+    api!(search 1 0 0 -> SearchResult,
         artist     1 0 0 : Option<&str>,
         album      1 0 0 : Option<&str>,
         title      1 0 0 : Option<&str>,
         any        1 0 0 : Option<&str>,
         count      1 0 0 : Option<u32>,
         offset     1 0 0 : Option<u32>,
-        newer_than 1 0 0 : Option<u64>
+        newer_than 1 0 0 : Option<Timestamp>
     );
-    api!(search2 1 4 0
+    api!(search2 1 4 0 -> SearchResult2,
         query            1 4 0 : &str,
         artist_count     1 4 0 : Option<u32>,
         artist_offset    1 4 0 : Option<u32>,
@@ -179,7 +301,7 @@ impl SubsonicClient {
         song_offset      1 4 0 : Option<u32>,
         music_folder_id 1 12 0 : Option<&str>
     );
-    api!(search3 1 4 0
+    api!(search3 1 4 0 -> SearchResult3,
         query            1 4 0 : &str,
         artist_count     1 4 0 : Option<u32>,
         artist_offset    1 4 0 : Option<u32>,
@@ -189,105 +311,377 @@ impl SubsonicClient {
         song_offset      1 4 0 : Option<u32>,
         music_folder_id 1 12 0 : Option<&str>
     );
-    api!(get_playlists 1 0 0
+    api!(get_playlists 1 0 0 -> Vec<Playlist>,
         username 1 8 0 : Option<&str>
     );
-    api!(get_playlist 1 0 0
-        id 1 0 0 : &str
+    api!(get_playlist 1 0 0 -> Playlist,
+        id 1 0 0 : &PlaylistId
     );
-    api!(delete_playlist 1 2 0
-        id 1 2 0 : &str
+    api!(delete_playlist 1 2 0 -> (),
+        id 1 2 0 : &PlaylistId
     );
-    api!(get_captions 1 14 0
-        id     1 14 0 : &str,
+    api!(@bytes get_captions 1 14 0
+        id     1 14 0 : &SongId,
         format 1 14 0 : Option<CaptionType>
     );
-    api!(set_rating 1 6 0
+    api!(set_rating 1 6 0 -> (),
         id     1 6 0 : &str,
         rating 1 6 0 : u32
     );
-    api!(scrobble 1 5 0
-        id         1 5 0 : &str,
-        time       1 8 0 : Option<u64>,
+    api!(scrobble 1 5 0 -> (),
+        id         1 5 0 : &SongId,
+        time       1 8 0 : Option<Timestamp>,
         submission 1 5 0 : Option<bool>
     );
-    api!(get_shares 1 6 0);
-    api!(create_share 1 6 0
-        id          1 6 0 : &str,
+    api!(get_shares 1 6 0 -> Vec<Share>);
+    api!(create_share 1 6 0 -> Vec<Share>,
+        id          1 6 0 : &[FolderItemId],
         description 1 6 0 : Option<&str>,
-        expires     1 6 0 : Option<u64>
+        expires     1 6 0 : Option<Timestamp>
     );
-    api!(update_share 1 6 0
-        id          1 6 0 : &str,
+    api!(update_share 1 6 0 -> (),
+        id          1 6 0 : &ShareId,
         description 1 6 0 : Option<&str>,
-        expires     1 6 0 : Option<u64>
+        expires     1 6 0 : Option<Timestamp>
     );
-    api!(delete_share 1 6 0
-        id 1 6 0 : &str
+    api!(delete_share 1 6 0 -> (),
+        id 1 6 0 : &ShareId
     );
-    api!(get_podcasts 1 6 0
+    api!(get_podcasts 1 6 0 -> Vec<Channel>,
         include_episodes 1 9 0 : Option<bool>,
-        id               1 9 0 : Option<&str>
+        id               1 9 0 : Option<&PodcastChannelId>
     );
-    api!(get_newest_podcasts 1 13 0
+    api!(get_newest_podcasts 1 13 0 -> Vec<Media>,
         count 1 13 0 : Option<u32>
     );
-    api!(refresh_podcasts 1 9 0);
-    api!(create_podcast_channel 1 9 0
+    api!(refresh_podcasts 1 9 0 -> ());
+    api!(create_podcast_channel 1 9 0 -> (),
         url 1 9 0 : &str
     );
-    api!(delete_podcast_channel 1 9 0
-        id 1 9 0 : &str
+    api!(delete_podcast_channel 1 9 0 -> (),
+        id 1 9 0 : &PodcastChannelId
     );
-    api!(delete_podcast_episode 1 9 0
-        id 1 9 0 : &str
+    api!(delete_podcast_episode 1 9 0 -> (),
+        id 1 9 0 : &PodcastEpisodeId
     );
-    api!(download_podcast_episode 1 9 0
-        id 1 9 0 : &str
+    api!(download_podcast_episode 1 9 0 -> (),
+        id 1 9 0 : &PodcastEpisodeId
     );
-    api!(get_internet_radio_stations 1 9 0);
-    api!(create_internet_radio_station 1 16 0
+    api!(get_internet_radio_stations 1 9 0 -> Vec<Radio>);
+    api!(create_internet_radio_station 1 16 0 -> (),
         stream_url   1 16 0 : &str,
         name         1 16 0 : &str,
         homepage_url 1 16 0 : Option<&str>
     );
-    api!(update_internet_radio_station 1 16 0
-        id           1 16 0 : &str,
+    api!(update_internet_radio_station 1 16 0 -> (),
+        id           1 16 0 : &RadioStationId,
         stream_url   1 16 0 : &str,
         name         1 16 0 : &str,
         homepage_url 1 16 0 : Option<&str>
     );
-    api!(delete_internet_radio_station 1 16 0
-        id 1 16 0 : &str
+    api!(delete_internet_radio_station 1 16 0 -> (),
+        id 1 16 0 : &RadioStationId
     );
-    api!(get_chat_messages 1 2 0
-        since 1 2 0 : u64
+    api!(get_chat_messages 1 2 0 -> Vec<ChatMessage>,
+        since 1 2 0 : Timestamp
     );
-    api!(add_chat_message 1 2 0
+    api!(add_chat_message 1 2 0 -> (),
         message 1 2 0 : &str
     );
-    api!(get_user 1 3 0
+    api!(get_user 1 3 0 -> User,
         username 1 3 0 : &str
     );
-    api!(get_users 1 8 0);
-    api!(delete_user 1 3 0
+    api!(get_users 1 8 0 -> Vec<User>);
+    api!(delete_user 1 3 0 -> (),
         username 1 3 0 : &str
     );
-    api!(change_password 1 1 0
+    api!(change_password 1 1 0 -> (),
         username 1 1 0 : &str,
         password 1 1 0 : &str // note that this could be hex encoded
     );
-    api!(get_bookmarks 1 9 0);
-    api!(create_bookmark 1 9 0
-        id       1 9 0 : &str,
+    api!(get_bookmarks 1 9 0 -> Vec<Bookmark>);
+    api!(create_bookmark 1 9 0 -> (),
+        id       1 9 0 : &SongId,
         position 1 9 0 : &str,
         comment  1 9 0 : Option<&str>
     );
+    api!(star 1 8 0 -> (),
+        id        1 8 0 : &[SongId],
+        album_id  1 8 0 : &[AlbumId],
+        artist_id 1 8 0 : &[ArtistId]
+    );
+    api!(unstar 1 8 0 -> (),
+        id        1 8 0 : &[SongId],
+        album_id  1 8 0 : &[AlbumId],
+        artist_id 1 8 0 : &[ArtistId]
+    );
+    api!(create_playlist 1 2 0 -> Option<Playlist>,
+        playlist_id 1 2 0 : Option<&PlaylistId>,
+        name        1 2 0 : Option<&str>,
+        song_id     1 2 0 : &[SongId]
+    );
+    api!(update_playlist 1 8 0 -> (),
+        playlist_id          1 8 0 : &PlaylistId,
+        name                 1 8 0 : Option<&str>,
+        comment              1 8 0 : Option<&str>,
+        public               1 8 0 : Option<bool>,
+        song_id_to_add       1 8 0 : &[SongId],
+        song_index_to_remove 1 8 0 : &[u32]
+    );
+
+    api!(@bytes stream 1 0 0
+        id                      1 0 0 : &SongId,
+        max_bit_rate            1 2 0 : Option<u32>,
+        format                  1 6 0 : Option<&str>,
+        time_offset             1 6 0 : Option<u32>,
+        size                    1 6 0 : Option<VideoSize>,
+        estimate_content_length 1 8 0 : Option<bool>,
+        converted              1 14 0 : Option<bool>
+    );
+    api!(@bytes download 1 0 0
+        id 1 0 0 : &SongId
+    );
+    api!(@bytes get_cover_art 1 0 0
+        id   1 0 0 : &str,
+        size 1 0 0 : Option<u32>
+    );
+    api!(@bytes get_avatar 1 8 0
+        username 1 8 0 : &str
+    );
+
+    // OpenSubsonic
+    api!(get_open_subsonic_extensions 1 0 0 -> Vec<OpenSubsonicExtension>);
+    api!([SongLyrics] get_lyrics_by_song_id 1 0 0 -> Vec<StructuredLyrics>,
+        id 1 0 0 : &SongId
+    );
+}
+
+impl SubsonicClient {
+    pub async fn get_album_list(
+        &self,
+        list_type: AlbumListType,
+        size: Option<u32>,
+        offset: Option<u32>,
+        music_folder_id: Option<&str>,
+    ) -> Result<Vec<Album>> {
+        self.fetch_album_list(&GET_ALBUM_LIST, list_type, size, offset, music_folder_id)
+            .await
+    }
+
+    pub async fn get_album_list2(
+        &self,
+        list_type: AlbumListType,
+        size: Option<u32>,
+        offset: Option<u32>,
+        music_folder_id: Option<&str>,
+    ) -> Result<Vec<Album>> {
+        self.fetch_album_list(&GET_ALBUM_LIST2, list_type, size, offset, music_folder_id)
+            .await
+    }
+
+    // shared body of get_album_list and get_album_list2, they only differ by name and version
+    async fn fetch_album_list(
+        &self,
+        endpoint: &Endpoint,
+        list_type: AlbumListType,
+        size: Option<u32>,
+        offset: Option<u32>,
+        music_folder_id: Option<&str>,
+    ) -> Result<Vec<Album>> {
+        if self.version < endpoint.since {
+            return Err(Error::APIVersionMismatch(self.version, endpoint.since));
+        }
+
+        // the type itself is versioned too
+        let type_since = list_type.since();
+        if self.version < type_since {
+            return Err(Error::ArgsVersionMismatch(self.version, type_since));
+        }
+
+        let mut args = vec![("type", list_type.to_string())];
+        args.extend(list_type.args());
+        if let Some(size) = size {
+            args.push(("size", size.to_string()));
+        }
+        if let Some(offset) = offset {
+            args.push(("offset", offset.to_string()));
+        }
+        if let Some(music_folder_id) = music_folder_id {
+            if !server_req!(self, 1, 11) {
+                return Err(Error::ArgsVersionMismatch(
+                    self.version,
+                    ApiVersion::new(1, 11, 0),
+                ));
+            }
+            args.push(("musicFolderId", music_folder_id.to_string()));
+        }
+        self.make_typed_req(endpoint.name, self.make_url(endpoint.name, &args)?).await
+    }
 
     // manual implimentation
-    // get_album_list, get_album_list2, create_playlist,
-    // update_playlist, stream, download, hls, get_avatar,
-    // star, unstar, jukebox_control, create_user, update_user,
-    // get_cover_art
-    // note that the multiparam stuff could be automated
+    // jukebox_control, create_user, update_user
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::{xml, MockServer};
+
+    // answers every list with an empty one
+    fn lists() -> MockServer {
+        MockServer::start(|req| {
+            if req.target.starts_with("/rest/getAlbumList2") {
+                xml("ok", "1.16.1", "<albumList2></albumList2>")
+            } else if req.target.starts_with("/rest/getAlbumList") {
+                xml("ok", "1.16.1", "<albumList></albumList>")
+            } else if req.target.starts_with("/rest/createShare") {
+                xml("ok", "1.16.1", "<shares></shares>")
+            } else {
+                xml("ok", "1.16.1", "")
+            }
+        })
+    }
+
+    fn client_at(server: &MockServer, version: ApiVersion) -> SubsonicClient {
+        SubsonicClient::builder(&server.url, "admin", "sesame")
+            .version(version)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn album_list_args() {
+        let server = lists();
+        let client = client_at(&server, ApiVersion::new(1, 16, 1));
+
+        client
+            .get_album_list(AlbumListType::AlphabeticalByArtist, Some(5), Some(10), None)
+            .await
+            .unwrap();
+        client
+            .get_album_list2(AlbumListType::ByYear { from: 1970, to: 1979 }, None, None, Some("2"))
+            .await
+            .unwrap();
+        client
+            .get_album_list2(AlbumListType::ByGenre { genre: "Rock & Roll".to_string() }, None, None, None)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert!(requests[0].target.starts_with("/rest/getAlbumList?"));
+        assert_eq!(requests[0].query("type").as_deref(), Some("alphabeticalByArtist"));
+        assert_eq!(requests[0].query("size").as_deref(), Some("5"));
+        assert_eq!(requests[0].query("offset").as_deref(), Some("10"));
+        assert!(!requests[0].has_query("fromYear"));
+
+        assert!(requests[1].target.starts_with("/rest/getAlbumList2?"));
+        assert_eq!(requests[1].query("type").as_deref(), Some("byYear"));
+        assert_eq!(requests[1].query("fromYear").as_deref(), Some("1970"));
+        assert_eq!(requests[1].query("toYear").as_deref(), Some("1979"));
+        assert_eq!(requests[1].query("musicFolderId").as_deref(), Some("2"));
+        assert!(!requests[1].has_query("size"));
+
+        assert_eq!(requests[2].query("type").as_deref(), Some("byGenre"));
+        assert_eq!(requests[2].query("genre").as_deref(), Some("Rock & Roll"));
+        assert!(!requests[2].has_query("fromYear"));
+    }
+
+    #[tokio::test]
+    async fn album_list_versions() {
+        let server = lists();
+
+        // getAlbumList2 is 1.8.0, and the year and genre types are 1.10.1
+        let client = client_at(&server, ApiVersion::new(1, 8, 0));
+        client
+            .get_album_list2(AlbumListType::Starred, None, None, None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            client
+                .get_album_list2(AlbumListType::ByYear { from: 1970, to: 1979 }, None, None, None)
+                .await,
+            Err(Error::ArgsVersionMismatch(_, since)) if since == ApiVersion::new(1, 10, 1)
+        ));
+        // musicFolderId came in 1.11.0
+        assert!(matches!(
+            client.get_album_list(AlbumListType::Random, None, None, Some("1")).await,
+            Err(Error::ArgsVersionMismatch(_, since)) if since == ApiVersion::new(1, 11, 0)
+        ));
+
+        let client = client_at(&server, ApiVersion::new(1, 7, 0));
+        assert!(matches!(
+            client.get_album_list2(AlbumListType::Random, None, None, None).await,
+            Err(Error::APIVersionMismatch(_, since)) if since == ApiVersion::new(1, 8, 0)
+        ));
+        // alphabeticalByName came in 1.8.0
+        assert!(matches!(
+            client.get_album_list(AlbumListType::AlphabeticalByName, None, None, None).await,
+            Err(Error::ArgsVersionMismatch(_, since)) if since == ApiVersion::new(1, 8, 0)
+        ));
+        client
+            .get_album_list(AlbumListType::Newest, None, None, None)
+            .await
+            .unwrap();
+
+        // only the two that were allowed went out
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    // every value of a param on a request, in order
+    fn values(req: &crate::mock::Request, name: &str) -> Vec<String> {
+        let url = reqwest::Url::parse(&format!("http://localhost{}", req.target)).unwrap();
+        url.query_pairs()
+            .filter(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn slices_repeat_params() {
+        let server = lists();
+        let client = client_at(&server, ApiVersion::new(1, 16, 1));
+
+        client
+            .star(&[SongId::from("1"), SongId::from("2")], &[AlbumId::from("10")], &[])
+            .await
+            .unwrap();
+        client.unstar(&[], &[], &[ArtistId::from("5")]).await.unwrap();
+        client
+            .create_playlist(None, Some("mix"), &[SongId::from("1"), SongId::from("2"), SongId::from("3")])
+            .await
+            .unwrap();
+        client
+            .update_playlist(&PlaylistId::from("7"), None, None, None, &[SongId::from("4")], &[0, 2])
+            .await
+            .unwrap();
+        client
+            .update_playlist(&PlaylistId::from("7"), Some("renamed"), None, None, &[], &[])
+            .await
+            .unwrap();
+        let shared = [SongId::from("1").into(), DirectoryId::from("10").into()];
+        client.create_share(&shared, Some("for you"), None).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(values(&requests[0], "id"), ["1", "2"]);
+        assert_eq!(values(&requests[0], "albumId"), ["10"]);
+        // a empty slice leaves the param off entirely
+        assert!(!requests[0].has_query("artistId"));
+
+        assert!(!requests[1].has_query("id"));
+        assert!(!requests[1].has_query("albumId"));
+        assert_eq!(values(&requests[1], "artistId"), ["5"]);
+
+        assert_eq!(values(&requests[2], "songId"), ["1", "2", "3"]);
+        assert!(!requests[2].has_query("playlistId"));
+
+        assert_eq!(values(&requests[3], "songIdToAdd"), ["4"]);
+        assert_eq!(values(&requests[3], "songIndexToRemove"), ["0", "2"]);
+
+        assert!(!requests[4].has_query("songIdToAdd"));
+        assert!(!requests[4].has_query("songIndexToRemove"));
+        assert_eq!(values(&requests[4], "name"), ["renamed"]);
+
+        assert_eq!(values(&requests[5], "id"), ["1", "10"]);
+        assert!(!requests[5].has_query("expires"));
+    }
 }
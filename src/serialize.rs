@@ -0,0 +1,325 @@
+use crate::deserialize::{Album, GenericList, SubsonicResp};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+const XMLNS: &str = "http://subsonic.org/restapi";
+
+// the writing side of deserialize, for serving the api
+// a response is laid out as its json form first, then the xml is made from that:
+// plain values become attributes, objects become child elements, arrays become
+// repeated elements and "value" becomes the text of the element
+impl SubsonicResp {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        let mut root = Map::new();
+        root.insert("subsonic-response".to_string(), Value::Object(self.fields()?));
+        serde_json::to_string(&root)
+    }
+
+    pub fn to_xml(&self) -> Result<String, serde_json::Error> {
+        let mut out = r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string();
+        write_element(
+            &mut out,
+            "subsonic-response",
+            &Value::Object(self.fields()?),
+            &[("xmlns", XMLNS)],
+        );
+        Ok(out)
+    }
+
+    // what goes inside subsonic-response, the payload is a key next to the status and version
+    fn fields(&self) -> Result<Map<String, Value>, serde_json::Error> {
+        let mut fields = Map::new();
+        fields.insert("status".to_string(), self.status.clone().into());
+        fields.insert("version".to_string(), self.version.clone().into());
+        if let Some(x) = &self.server_type {
+            fields.insert("type".to_string(), x.clone().into());
+        }
+        if let Some(x) = &self.server_version {
+            fields.insert("serverVersion".to_string(), x.clone().into());
+        }
+        if self.open_subsonic {
+            fields.insert("openSubsonic".to_string(), true.into());
+        }
+        if let Some(resp) = &self.resp {
+            if let Value::Object(payload) = serde_json::to_value(resp)? {
+                fields.extend(payload);
+            }
+        }
+        // a missing value is left out instead of being sent as null
+        fields.values_mut().for_each(drop_nulls);
+        Ok(fields)
+    }
+}
+
+fn drop_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, x| !x.is_null());
+            map.values_mut().for_each(drop_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(drop_nulls),
+        _ => (),
+    }
+}
+
+fn write_element(out: &mut String, name: &str, value: &Value, attributes: &[(&str, &str)]) {
+    let fields = match value {
+        Value::Null => return,
+        Value::Array(items) => {
+            for x in items {
+                write_element(out, name, x, &[]);
+            }
+            return;
+        }
+        Value::Object(fields) => fields,
+        // a plain value in a list, ex: <allowedUser>sindre</allowedUser>
+        x => {
+            out.push_str(&format!("<{0}>{1}</{0}>", name, escape(&text(x))));
+            return;
+        }
+    };
+
+    out.push('<');
+    out.push_str(name);
+    let plain = fields
+        .iter()
+        .filter(|(k, v)| *k != "value" && !v.is_object() && !v.is_array() && !v.is_null())
+        .map(|(k, v)| (k.as_str(), text(v)));
+    for (k, v) in attributes.iter().map(|(k, v)| (*k, v.to_string())).chain(plain) {
+        out.push_str(&format!(r#" {}="{}""#, k, escape(&v)));
+    }
+
+    let content = fields.get("value").map(text);
+    let children: Vec<_> = fields
+        .iter()
+        .filter(|(_, v)| v.is_object() || v.is_array())
+        .collect();
+    if content.is_none() && children.is_empty() {
+        out.push_str("/>");
+        return;
+    }
+    out.push('>');
+    if let Some(content) = content {
+        out.push_str(&escape(&content));
+    }
+    for (k, v) in children {
+        write_element(out, k, v, &[]);
+    }
+    out.push_str(&format!("</{}>", name));
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(x) => x.clone(),
+        x => x.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.chars().fold(String::with_capacity(text.len()), |mut accum, x| {
+        match x {
+            '&' => accum.push_str("&amp;"),
+            '<' => accum.push_str("&lt;"),
+            '>' => accum.push_str("&gt;"),
+            '"' => accum.push_str("&quot;"),
+            '\'' => accum.push_str("&apos;"),
+            x => accum.push(x),
+        }
+        accum
+    })
+}
+
+// a list as it's sent, a wrapper holding the items by name, ex: "genres": {"genre": [...]}
+struct Wrapped<'a, T>(&'static str, &'a GenericList<T>);
+
+impl<T: Serialize> Serialize for Wrapped<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(items) = &self.1.items {
+            map.serialize_entry(self.0, items)?;
+        }
+        map.end()
+    }
+}
+
+// the items are named by the list they're in, ex: the songs of randomSongs are <song>
+// these are the serialize_with of the list variants of SubsonicInfo
+macro_rules! item_names {
+    ($($name:ident = $item:literal),* $(,)?) => {$(
+        pub(crate) fn $name<S: Serializer, T: Serialize>(
+            list: &GenericList<T>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            Wrapped($item, list).serialize(serializer)
+        }
+    )*};
+}
+
+item_names!(
+    album = "album",
+    bookmark = "bookmark",
+    channel = "channel",
+    chat_message = "chatMessage",
+    entry = "entry",
+    episode = "episode",
+    genre = "genre",
+    internet_radio_station = "internetRadioStation",
+    music_folder = "musicFolder",
+    playlist = "playlist",
+    share = "share",
+    song = "song",
+    structured_lyrics = "structuredLyrics",
+    user = "user",
+    video = "video",
+);
+
+// the folder based lists (albumList, starred, searchResult2) call an album's name "title"
+// it's written as "name" everywhere else, the id3 way, so it's renamed on the way out
+pub(crate) fn folder_album_list<S: Serializer>(
+    list: &GenericList<Album>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    folder_albums(&Wrapped("album", list), serializer)
+}
+
+pub(crate) fn folder_albums<S: Serializer, T: Serialize>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::Error;
+    let mut value = serde_json::to_value(value).map_err(S::Error::custom)?;
+    if let Some(Value::Array(albums)) = value.get_mut("album") {
+        for album in albums {
+            if let Value::Object(fields) = album {
+                // rebuilt so the title stays where the name was
+                *fields = std::mem::take(fields)
+                    .into_iter()
+                    .map(|(k, v)| if k == "name" { ("title".to_string(), v) } else { (k, v) })
+                    .collect();
+            }
+        }
+    }
+    value.serialize(serializer)
+}
+
+// OpenSubsonic lists without a wrapper, ex: "openSubsonicExtensions": [...]
+pub(crate) fn unwrapped<S: Serializer, T: Serialize>(
+    list: &GenericList<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    list.items.as_deref().unwrap_or_default().serialize(serializer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::deserialize::{Genre, SearchResult3, Starred, SubsonicInfo};
+    use crate::AlbumId;
+    use std::fs::{read_dir, read_to_string};
+
+    #[test]
+    fn round_trips() {
+        // the fixtures, along with anything downloaded into test/ (see deserialize's deser_tests)
+        let files = read_dir("test/fixtures")
+            .unwrap()
+            .chain(read_dir("test").unwrap())
+            .map(|x| x.unwrap().path());
+        let mut checked = 0;
+        for path in files {
+            let resp = match path.extension().and_then(|x| x.to_str()) {
+                Some("xml") => SubsonicResp::from_xml(&read_to_string(&path).unwrap()).unwrap(),
+                Some("json") => SubsonicResp::from_json(&read_to_string(&path).unwrap()).unwrap(),
+                _ => continue,
+            };
+            let xml = resp.to_xml().unwrap();
+            assert_eq!(SubsonicResp::from_xml(&xml).unwrap(), resp, "{:?} as xml", path);
+            let json = resp.to_json().unwrap();
+            assert_eq!(SubsonicResp::from_json(&json).unwrap(), resp, "{:?} as json", path);
+            checked += 1;
+        }
+        assert_ne!(checked, 0, "no fixtures found");
+    }
+
+    #[test]
+    fn layout() {
+        let resp = SubsonicResp {
+            status: "ok".to_string(),
+            version: "1.16.1".to_string(),
+            server_type: None,
+            server_version: None,
+            open_subsonic: false,
+            resp: Some(SubsonicInfo::Genres(GenericList {
+                items: Some(vec![Genre {
+                    song_count: 2,
+                    album_count: 1,
+                    name: "Rock & Roll".to_string(),
+                }]),
+            })),
+        };
+        assert_eq!(
+            resp.to_xml().unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?><subsonic-response xmlns="http://subsonic.org/restapi" status="ok" version="1.16.1"><genres><genre songCount="2" albumCount="1">Rock &amp; Roll</genre></genres></subsonic-response>"#
+        );
+        assert_eq!(
+            resp.to_json().unwrap(),
+            r#"{"subsonic-response":{"status":"ok","version":"1.16.1","genres":{"genre":[{"songCount":2,"albumCount":1,"value":"Rock & Roll"}]}}}"#
+        );
+
+        // the extensions go out the way OpenSubsonic sends them, and nothing missing is sent as null
+        let json = read_to_string("test/fixtures/openSubsonicExtensions.json").unwrap();
+        let resp = SubsonicResp::from_json(&json).unwrap();
+        let xml = resp.to_xml().unwrap();
+        assert!(xml.contains(r#"<openSubsonicExtensions name="songLyrics"><versions>1</versions><versions>2</versions></openSubsonicExtensions>"#));
+        assert!(resp.to_json().unwrap().contains(r#""openSubsonicExtensions":[{"name":"#));
+
+        let song = SubsonicResp::from_xml(&read_to_string("test/fixtures/song.xml").unwrap()).unwrap();
+        let json = song.to_json().unwrap();
+        assert!(!json.contains("null"));
+        assert!(json.contains(r#""duration":1227"#));
+        assert!(json.contains(r#""moods":["sad","energetic"]"#));
+        let xml = song.to_xml().unwrap();
+        assert!(xml.contains(r#"<moods>sad</moods><moods>energetic</moods>"#));
+        assert!(xml.contains(r#"<contributors role="performer" subRole="Bass"><artist "#));
+    }
+
+    #[test]
+    fn folder_album_titles() {
+        let album = Album {
+            id: AlbumId::from("11"),
+            title: "Bad".to_string(),
+            artist: Some("Michael Jackson".to_string()),
+            ..Default::default()
+        };
+        let resp = |info| SubsonicResp {
+            status: "ok".to_string(),
+            version: "1.16.1".to_string(),
+            server_type: None,
+            server_version: None,
+            open_subsonic: false,
+            resp: Some(info),
+        };
+        let list = GenericList { items: Some(vec![album.clone()]) };
+
+        let xml = resp(SubsonicInfo::AlbumList(list.clone())).to_xml().unwrap();
+        assert!(xml.contains(r#"<albumList><album id="11" title="Bad" artist="Michael Jackson"/></albumList>"#), "{}", xml);
+        let xml = resp(SubsonicInfo::AlbumList2(list)).to_xml().unwrap();
+        assert!(xml.contains(r#"<albumList2><album id="11" name="Bad" artist="Michael Jackson"/></albumList2>"#), "{}", xml);
+
+        let starred = Starred { albums: vec![album.clone()], ..Default::default() };
+        let found = SearchResult3 { albums: vec![album], ..Default::default() };
+        for (info, key) in [
+            (SubsonicInfo::Starred(starred.clone()), "title"),
+            (SubsonicInfo::SearchResult2(found.clone()), "title"),
+            (SubsonicInfo::Starred2(starred), "name"),
+            (SubsonicInfo::SearchResult3(found), "name"),
+        ] {
+            let resp = resp(info);
+            let json = resp.to_json().unwrap();
+            assert!(json.contains(&format!(r#""id":"11","{}":"Bad""#, key)), "{}", json);
+            // and it still reads back
+            assert_eq!(SubsonicResp::from_json(&json).unwrap(), resp);
+            assert_eq!(SubsonicResp::from_xml(&resp.to_xml().unwrap()).unwrap(), resp);
+        }
+    }
+}
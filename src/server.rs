@@ -0,0 +1,1169 @@
+use super::*;
+use builder::LATEST_API_VERSION;
+use deserialize::*;
+use restapi::Endpoint;
+use std::fmt::Display;
+use std::future::{ready, Future, Ready};
+use std::str::FromStr;
+use stream::{MediaStream, VideoSize};
+
+const XML_TYPE: &str = "text/xml; charset=utf-8";
+const JSON_TYPE: &str = "application/json; charset=utf-8";
+
+// the serving side of the api, for test servers and custom backends
+// a SubsonicBackend has the library and the users, SubsonicServer speaks the protocol in front of it:
+// it reads /rest/* requests, checks the login and the versions in restapi::ENDPOINTS,
+// then writes what the backend gave back as a subsonic response in the format asked for
+// the http server itself is left to whatever the app already uses, handle() is all of the glue
+
+// search2 and search3, the counts and offsets left out are filled in with the spec's defaults
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub query: String,
+    pub artist_count: u32,
+    pub artist_offset: u32,
+    pub album_count: u32,
+    pub album_offset: u32,
+    pub song_count: u32,
+    pub song_offset: u32,
+    pub music_folder_id: Option<String>,
+}
+
+// getAlbumList and getAlbumList2
+#[derive(Debug, Clone)]
+pub struct AlbumListQuery {
+    pub list_type: AlbumListType,
+    pub size: u32,
+    pub offset: u32,
+    pub music_folder_id: Option<String>,
+}
+
+// the optional params of stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamOptions {
+    pub max_bit_rate: Option<u32>,
+    pub format: Option<String>,
+    pub time_offset: Option<u32>,
+    pub size: Option<VideoSize>,
+    pub estimate_content_length: bool,
+    pub converted: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaylistUpdate {
+    pub playlist_id: PlaylistId,
+    pub name: Option<String>,
+    pub comment: Option<String>,
+    pub public: Option<bool>,
+    pub song_ids_to_add: Vec<SongId>,
+    pub song_indexes_to_remove: Vec<u32>,
+}
+
+// what star and unstar are given, any of the lists can be empty
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StarItems {
+    pub ids: Vec<SongId>,
+    pub album_ids: Vec<AlbumId>,
+    pub artist_ids: Vec<ArtistId>,
+}
+
+// the methods every backend gets a default for, they're given the user the request logged in as
+// and answer with a "not implemented" error until the backend has them
+macro_rules! backend {
+    ($($name:ident($($arg:ident: $t:ty),*) -> $ret:ty;)*) => {
+        // what a server is backed by
+        // only password has to be written, the rest is whatever part of the api the backend serves
+        // implementations can use async fn, ex: async fn get_album(&self, user: &str, id: &AlbumId) -> Result<Album>
+        // a error goes back to the client as is when it's a Error::ServerError, as a generic error otherwise
+        pub trait SubsonicBackend: Send + Sync {
+            // the password of a user, None for a user that doesn't exist
+            // token logins are md5(password + salt), so this is the password itself and not a hash of it
+            fn password(&self, username: &str) -> impl Future<Output = Result<Option<Secret>>> + Send;
+
+            // the user a api key belongs to, None for a key that isn't valid
+            // only asked when extensions() has Extension::ApiKeyAuthentication
+            #[allow(unused_variables)]
+            fn api_key_user(&self, key: &str) -> impl Future<Output = Result<Option<String>>> + Send {
+                not_implemented("api_key_user")
+            }
+
+            // if a user manages the others: getUsers, deleteUser, and getUser or changePassword for someone else
+            // SubsonicServer checks this before those reach the backend, a user can always see and change their own
+            // the default goes by the adminRole of get_user
+            fn is_admin(&self, username: &str) -> impl Future<Output = Result<bool>> + Send {
+                async move { Ok(self.get_user(username, username).await?.admin_role) }
+            }
+
+            // the OpenSubsonic extensions the backend handles, formPost is always advertised on top
+            fn extensions(&self) -> Vec<Extension> {
+                Vec::new()
+            }
+
+            $(
+                #[allow(unused_variables)]
+                fn $name(&self, user: &str, $($arg: $t),*) -> impl Future<Output = Result<$ret>> + Send {
+                    not_implemented(stringify!($name))
+                }
+            )*
+        }
+    };
+}
+
+backend! {
+    // browsing
+    get_music_folders() -> Vec<MusicFolder>;
+    get_indexes(music_folder_id: Option<&str>, if_modified_since: Option<Timestamp>) -> Indexes;
+    get_music_directory(id: &DirectoryId) -> Directory;
+    get_genres() -> Vec<Genre>;
+    get_artists(music_folder_id: Option<&str>) -> Artists;
+    get_artist(id: &ArtistId) -> Artist;
+    get_album(id: &AlbumId) -> Album;
+    get_song(id: &SongId) -> Media;
+    // serves getAlbumList and getAlbumList2
+    get_album_list(query: &AlbumListQuery) -> Vec<Album>;
+    get_songs_by_genre(genre: &str, count: u32, offset: u32, music_folder_id: Option<&str>) -> Vec<Media>;
+
+    // serves search2 and search3
+    search(query: &SearchQuery) -> SearchResult3;
+
+    // media
+    stream(id: &SongId, options: &StreamOptions) -> MediaStream;
+    download(id: &SongId) -> MediaStream;
+    get_cover_art(id: &str, size: Option<u32>) -> MediaStream;
+
+    // playlists
+    get_playlists(username: Option<&str>) -> Vec<Playlist>;
+    get_playlist(id: &PlaylistId) -> Playlist;
+    // a new playlist when playlist_id is None, otherwise the songs of that one are replaced
+    create_playlist(playlist_id: Option<&PlaylistId>, name: Option<&str>, song_ids: &[SongId]) -> Playlist;
+    update_playlist(update: &PlaylistUpdate) -> ();
+    delete_playlist(id: &PlaylistId) -> ();
+
+    // stars, get_starred serves getStarred and getStarred2
+    star(items: &StarItems) -> ();
+    unstar(items: &StarItems) -> ();
+    get_starred(music_folder_id: Option<&str>) -> Starred;
+
+    // asked once for every song in the request
+    scrobble(id: &SongId, time: Option<Timestamp>, submission: bool) -> ();
+
+    // users, createUser and updateUser aren't in restapi::ENDPOINTS yet so they aren't served
+    get_user(username: &str) -> User;
+    get_users() -> Vec<User>;
+    delete_user(username: &str) -> ();
+    // the new password as it was meant, a enc: hex password is decoded first
+    change_password(username: &str, password: &Secret) -> ();
+}
+
+fn not_implemented<T>(name: &str) -> Ready<Result<T>> {
+    ready(Err(Error::ServerError(
+        SubsonicErrorCode::Generic,
+        format!("{} is not implemented by this server", name),
+    )))
+}
+
+// what goes back out, the caller turns this into a http response
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: String,
+    pub body: Body,
+}
+
+#[derive(Debug)]
+pub enum Body {
+    // a subsonic response, or the reason there isn't one
+    Text(String),
+    // the binary endpoints: stream, download and getCoverArt
+    Media(MediaStream),
+}
+
+impl Response {
+    fn text(status: u16, content_type: &str, body: String) -> Self {
+        Response {
+            status,
+            content_type: content_type.to_string(),
+            body: Body::Text(body),
+        }
+    }
+
+    fn not_found() -> Self {
+        Response::text(404, "text/plain; charset=utf-8", "not found".to_string())
+    }
+}
+
+// what a endpoint answers with, before it's written out
+enum Reply {
+    Info(Box<Option<SubsonicInfo>>),
+    Media(MediaStream),
+}
+
+// the params of a request, from the query and the form body together
+struct Params(Vec<(String, String)>);
+
+impl Params {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0
+            .iter()
+            .filter(move |(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn required(&self, name: &str) -> Result<&str> {
+        self.get(name).ok_or_else(|| missing(name))
+    }
+
+    fn parse<T: FromStr>(&self, name: &str) -> Result<Option<T>>
+    where
+        T::Err: Display,
+    {
+        self.get(name).map(|x| parse_value(name, x)).transpose()
+    }
+
+    fn parse_all<T: FromStr>(&self, name: &str) -> Result<Vec<T>>
+    where
+        T::Err: Display,
+    {
+        self.all(name).map(|x| parse_value(name, x)).collect()
+    }
+
+    fn required_parse<T: FromStr>(&self, name: &str) -> Result<T>
+    where
+        T::Err: Display,
+    {
+        parse_value(name, self.required(name)?)
+    }
+
+    // times are sent as milliseconds since the epoch
+    fn millis(&self, name: &str) -> Result<Option<Timestamp>> {
+        Ok(self.parse::<i64>(name)?.map(Timestamp::from_millis))
+    }
+}
+
+fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T>
+where
+    T::Err: Display,
+{
+    value.parse().map_err(|e| {
+        Error::ServerError(
+            SubsonicErrorCode::Generic,
+            format!("bad value {:?} for {}: {}", value, name, e),
+        )
+    })
+}
+
+fn missing(name: &str) -> Error {
+    Error::ServerError(
+        SubsonicErrorCode::MissingParameter,
+        format!("required parameter {} is missing", name),
+    )
+}
+
+// a subsonic server in front of a backend, ex:
+// let server = SubsonicServer::new(library).version(ApiVersion::new(1, 16, 1));
+// let response = server.handle("/rest/getAlbum?id=1&u=admin&p=sesame&v=1.16.1&c=app", b"").await;
+pub struct SubsonicServer<B> {
+    backend: B,
+    version: ApiVersion,
+    kind: String,
+    server_version: Option<String>,
+}
+
+impl<B: SubsonicBackend> SubsonicServer<B> {
+    pub fn new(backend: B) -> Self {
+        SubsonicServer {
+            backend,
+            version: LATEST_API_VERSION,
+            kind: env!("CARGO_PKG_NAME").to_string(),
+            server_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }
+    }
+
+    // the api version served, endpoints and args newer than this are refused
+    pub fn version(mut self, version: ApiVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    // what the server calls itself in its responses (the OpenSubsonic type and serverVersion)
+    pub fn server_info(mut self, kind: &str, version: Option<&str>) -> Self {
+        self.kind = kind.to_string();
+        self.server_version = version.map(|x| x.to_string());
+        self
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn api_version(&self) -> ApiVersion {
+        self.version
+    }
+
+    pub fn supports(&self, extension: &Extension) -> bool {
+        *extension == Extension::FormPost || self.backend.extensions().contains(extension)
+    }
+
+    // answers a request, given its target (path and query, ex: /rest/ping.view?u=admin&...)
+    // and its body, which is read as a form for a formPost request and should be empty otherwise
+    // anything outside of /rest/ is a 404, a endpoint under it this crate doesn't know is error 70
+    pub async fn handle(&self, target: &str, body: &[u8]) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let name = match path.strip_prefix("/rest/") {
+            Some(x) => x.strip_suffix(".view").unwrap_or(x),
+            None => return Response::not_found(),
+        };
+        let params = Params(
+            form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .chain(form_urlencoded::parse(body).into_owned())
+                .collect(),
+        );
+        let format = match params.get("f") {
+            Some("json") => ResponseFormat::Json,
+            _ => ResponseFormat::Xml,
+        };
+
+        let endpoint = match restapi::endpoint(name) {
+            Some(x) => x,
+            None => {
+                return self.respond(
+                    format,
+                    Some(SubsonicInfo::Error {
+                        code: SubsonicErrorCode::NotFound,
+                        message: Some(format!("{} is not a endpoint", name)),
+                    }),
+                )
+            }
+        };
+        match self.answer(endpoint, &params).await {
+            Ok(Reply::Info(info)) => self.respond(format, *info),
+            Ok(Reply::Media(media)) => Response {
+                status: 200,
+                content_type: media
+                    .content_type
+                    .clone()
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+                body: Body::Media(media),
+            },
+            // the errors are a normal response, same as any other subsonic server
+            Err(e) => {
+                let (code, message) = match e {
+                    Error::ServerError(code, message) => (code, message),
+                    e => (SubsonicErrorCode::Generic, e.to_string()),
+                };
+                self.respond(
+                    format,
+                    Some(SubsonicInfo::Error {
+                        code,
+                        message: Some(message),
+                    }),
+                )
+            }
+        }
+    }
+
+    fn respond(&self, format: ResponseFormat, info: Option<SubsonicInfo>) -> Response {
+        let status = match info {
+            Some(SubsonicInfo::Error { .. }) => "failed",
+            _ => "ok",
+        };
+        let resp = SubsonicResp {
+            status: status.to_string(),
+            version: self.version.to_string(),
+            server_type: Some(self.kind.clone()),
+            server_version: self.server_version.clone(),
+            open_subsonic: true,
+            resp: info,
+        };
+        let (content_type, body) = match format {
+            ResponseFormat::Xml => (XML_TYPE, resp.to_xml()),
+            ResponseFormat::Json => (JSON_TYPE, resp.to_json()),
+        };
+        match body {
+            Ok(body) => Response::text(200, content_type, body),
+            Err(e) => Response::text(500, "text/plain; charset=utf-8", e.to_string()),
+        }
+    }
+
+    async fn answer(&self, endpoint: &Endpoint, params: &Params) -> Result<Reply> {
+        let user = self.login(params).await?;
+        let client = params.required_parse::<ApiVersion>("v")?;
+        self.check_versions(endpoint, client, params)?;
+        if let Some(extension) = &endpoint.extension {
+            if !self.supports(extension) {
+                return Err(Error::ExtensionNotSupported(extension.clone()));
+            }
+        }
+        self.route(endpoint.name, &user, client, params).await
+    }
+
+    // works out who the request is from, by api key, token or password
+    async fn login(&self, params: &Params) -> Result<String> {
+        let fail = |code, message: &str| Error::ServerError(code, message.to_string());
+        let username = match (params.get("apiKey"), params.get("u")) {
+            (Some(_), Some(_)) => {
+                return Err(fail(
+                    SubsonicErrorCode::ConflictingAuthMechanisms,
+                    "an api key stands in for the username, send one or the other",
+                ))
+            }
+            (Some(key), None) => {
+                if !self.supports(&Extension::ApiKeyAuthentication) {
+                    return Err(fail(
+                        SubsonicErrorCode::AuthMechanismNotSupported,
+                        "api keys are not supported",
+                    ));
+                }
+                return self
+                    .backend
+                    .api_key_user(key)
+                    .await?
+                    .ok_or_else(|| fail(SubsonicErrorCode::InvalidApiKey, "invalid api key"));
+            }
+            (None, Some(username)) => username,
+            (None, None) => return Err(missing("u")),
+        };
+
+        let password = self.backend.password(username).await?;
+        let valid = match (params.get("t"), params.get("s"), params.get("p")) {
+            // a short salt makes the token easy to replay, the same as sending the password
+            (Some(_), Some(salt), _) if salt.len() < auth::MIN_SALT_LEN => {
+                return Err(fail(
+                    SubsonicErrorCode::WrongCredentials,
+                    &format!("the salt needs at least {} characters", auth::MIN_SALT_LEN),
+                ))
+            }
+            (Some(token), Some(salt), _) => password
+                .as_ref()
+                .is_some_and(|x| same(&auth::token(x.expose(), salt), &token.to_ascii_lowercase())),
+            (_, _, Some(sent)) => {
+                let sent = Secret::from(decode_password(sent)?);
+                password.as_ref().is_some_and(|x| same(x.expose(), sent.expose()))
+            }
+            _ => return Err(missing("p")),
+        };
+        if valid {
+            Ok(username.to_string())
+        } else {
+            Err(fail(SubsonicErrorCode::WrongCredentials, "wrong username or password"))
+        }
+    }
+
+    // the same table the client checks against, from this side:
+    // anything newer than the server's version is error 30, whatever version the client sent
+    // a older client is still served what's newer than it, only a older major version is error 20
+    fn check_versions(&self, endpoint: &Endpoint, client: ApiVersion, params: &Params) -> Result<()> {
+        // a different major version is a different protocol, whichever side is behind has to upgrade
+        if client.major > self.version.major {
+            return Err(self.version_error(client, "the api", client));
+        }
+        if client.major < self.version.major {
+            return Err(self.version_error(client, "the api", self.version));
+        }
+        self.check_since(client, endpoint.name, endpoint.since)?;
+        for (name, _) in &params.0 {
+            if let Some(since) = endpoint.arg_since(name) {
+                self.check_since(client, name, since)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_since(&self, client: ApiVersion, what: &str, since: ApiVersion) -> Result<()> {
+        if since > self.version {
+            return Err(self.version_error(client, what, since));
+        }
+        Ok(())
+    }
+
+    fn version_error(&self, client: ApiVersion, what: &str, since: ApiVersion) -> Error {
+        let code = if since > self.version {
+            SubsonicErrorCode::ServerTooOld
+        } else {
+            SubsonicErrorCode::ClientTooOld
+        };
+        Error::ServerError(
+            code,
+            format!(
+                "{} needs api {}, the client is on {} and the server on {}",
+                what, since, client, self.version
+            ),
+        )
+    }
+
+    async fn route(&self, name: &str, user: &str, client: ApiVersion, params: &Params) -> Result<Reply> {
+        let backend = &self.backend;
+        let folder = params.get("musicFolderId");
+        let info = match name {
+            "ping" => None,
+            // there's nothing to license, so it never runs out
+            "getLicense" => Some(SubsonicInfo::License(License {
+                valid: true,
+                email: String::new(),
+                license_expires: Timestamp::from_millis(253_402_300_799_000),
+            })),
+            "getOpenSubsonicExtensions" => {
+                let mut extensions = vec![Extension::FormPost];
+                extensions.extend(
+                    backend
+                        .extensions()
+                        .into_iter()
+                        .filter(|x| *x != Extension::FormPost),
+                );
+                let extensions = extensions
+                    .iter()
+                    .map(|x| OpenSubsonicExtension {
+                        name: x.name().to_string(),
+                        versions: vec![1],
+                    })
+                    .collect::<Vec<_>>();
+                Some(SubsonicInfo::OpenSubsonicExtensions(extensions.into()))
+            }
+
+            "getMusicFolders" => Some(SubsonicInfo::MusicFolders(
+                backend.get_music_folders(user).await?.into(),
+            )),
+            "getIndexes" => Some(SubsonicInfo::Indexes(
+                backend
+                    .get_indexes(user, folder, params.millis("ifModifiedSince")?)
+                    .await?,
+            )),
+            "getMusicDirectory" => Some(SubsonicInfo::Directory(
+                backend
+                    .get_music_directory(user, &params.required("id")?.into())
+                    .await?,
+            )),
+            "getGenres" => Some(SubsonicInfo::Genres(backend.get_genres(user).await?.into())),
+            "getArtists" => Some(SubsonicInfo::Artists(backend.get_artists(user, folder).await?)),
+            "getArtist" => Some(SubsonicInfo::Artist(
+                backend
+                    .get_artist(user, &params.required("id")?.into())
+                    .await?,
+            )),
+            "getAlbum" => Some(SubsonicInfo::Album(
+                backend
+                    .get_album(user, &params.required("id")?.into())
+                    .await?,
+            )),
+            "getSong" => Some(SubsonicInfo::Song(Box::new(
+                backend
+                    .get_song(user, &params.required("id")?.into())
+                    .await?,
+            ))),
+            "getAlbumList" | "getAlbumList2" => {
+                let query = self.album_list_query(client, params)?;
+                let albums = backend.get_album_list(user, &query).await?.into();
+                Some(match name {
+                    "getAlbumList" => SubsonicInfo::AlbumList(albums),
+                    _ => SubsonicInfo::AlbumList2(albums),
+                })
+            }
+            "getSongsByGenre" => Some(SubsonicInfo::SongsByGenre(
+                backend
+                    .get_songs_by_genre(
+                        user,
+                        params.required("genre")?,
+                        params.parse("count")?.unwrap_or(10).min(500),
+                        params.parse("offset")?.unwrap_or(0),
+                        folder,
+                    )
+                    .await?
+                    .into(),
+            )),
+
+            "search2" | "search3" => {
+                let count = |name| Ok::<_, Error>(params.parse(name)?.unwrap_or(20));
+                let offset = |name| Ok::<_, Error>(params.parse(name)?.unwrap_or(0));
+                let query = SearchQuery {
+                    query: params.required("query")?.to_string(),
+                    artist_count: count("artistCount")?,
+                    artist_offset: offset("artistOffset")?,
+                    album_count: count("albumCount")?,
+                    album_offset: offset("albumOffset")?,
+                    song_count: count("songCount")?,
+                    song_offset: offset("songOffset")?,
+                    music_folder_id: folder.map(|x| x.to_string()),
+                };
+                let found = backend.search(user, &query).await?;
+                Some(match name {
+                    "search2" => SubsonicInfo::SearchResult2(found),
+                    _ => SubsonicInfo::SearchResult3(found),
+                })
+            }
+
+            "stream" => {
+                let options = StreamOptions {
+                    max_bit_rate: params.parse("maxBitRate")?,
+                    format: params.get("format").map(|x| x.to_string()),
+                    time_offset: params.parse("timeOffset")?,
+                    size: params.get("size").map(video_size).transpose()?,
+                    estimate_content_length: params.parse("estimateContentLength")?.unwrap_or(false),
+                    converted: params.parse("converted")?.unwrap_or(false),
+                };
+                let id = params.required("id")?.into();
+                return Ok(Reply::Media(backend.stream(user, &id, &options).await?));
+            }
+            "download" => {
+                let id = params.required("id")?.into();
+                return Ok(Reply::Media(backend.download(user, &id).await?));
+            }
+            "getCoverArt" => {
+                let id = params.required("id")?;
+                return Ok(Reply::Media(
+                    backend.get_cover_art(user, id, params.parse("size")?).await?,
+                ));
+            }
+
+            "getPlaylists" => Some(SubsonicInfo::Playlists(
+                backend
+                    .get_playlists(user, params.get("username"))
+                    .await?
+                    .into(),
+            )),
+            "getPlaylist" => Some(SubsonicInfo::Playlist(
+                backend
+                    .get_playlist(user, &params.required("id")?.into())
+                    .await?,
+            )),
+            "createPlaylist" => {
+                let playlist_id = params.get("playlistId").map(PlaylistId::from);
+                let name = params.get("name");
+                if playlist_id.is_none() && name.is_none() {
+                    return Err(missing("playlistId or name"));
+                }
+                let song_ids: Vec<SongId> = params.all("songId").map(SongId::from).collect();
+                Some(SubsonicInfo::Playlist(
+                    backend
+                        .create_playlist(user, playlist_id.as_ref(), name, &song_ids)
+                        .await?,
+                ))
+            }
+            "updatePlaylist" => {
+                let update = PlaylistUpdate {
+                    playlist_id: params.required("playlistId")?.into(),
+                    name: params.get("name").map(|x| x.to_string()),
+                    comment: params.get("comment").map(|x| x.to_string()),
+                    public: params.parse("public")?,
+                    song_ids_to_add: params.all("songIdToAdd").map(SongId::from).collect(),
+                    song_indexes_to_remove: params.parse_all("songIndexToRemove")?,
+                };
+                backend.update_playlist(user, &update).await?;
+                None
+            }
+            "deletePlaylist" => {
+                backend
+                    .delete_playlist(user, &params.required("id")?.into())
+                    .await?;
+                None
+            }
+
+            "star" | "unstar" => {
+                let items = StarItems {
+                    ids: params.all("id").map(SongId::from).collect(),
+                    album_ids: params.all("albumId").map(AlbumId::from).collect(),
+                    artist_ids: params.all("artistId").map(ArtistId::from).collect(),
+                };
+                match name {
+                    "star" => backend.star(user, &items).await?,
+                    _ => backend.unstar(user, &items).await?,
+                }
+                None
+            }
+            "getStarred" => Some(SubsonicInfo::Starred(backend.get_starred(user, folder).await?)),
+            "getStarred2" => Some(SubsonicInfo::Starred2(backend.get_starred(user, folder).await?)),
+
+            // the nth time goes with the nth id
+            "scrobble" => {
+                params.required("id")?;
+                let times = params.parse_all::<i64>("time")?;
+                let submission = params.parse("submission")?.unwrap_or(true);
+                for (i, id) in params.all("id").enumerate() {
+                    let time = times.get(i).copied().map(Timestamp::from_millis);
+                    backend.scrobble(user, &id.into(), time, submission).await?;
+                }
+                None
+            }
+
+            "getUser" => {
+                let username = params.required("username")?;
+                self.check_admin(user, Some(username)).await?;
+                Some(SubsonicInfo::User(backend.get_user(user, username).await?))
+            }
+            "getUsers" => {
+                self.check_admin(user, None).await?;
+                Some(SubsonicInfo::Users(backend.get_users(user).await?.into()))
+            }
+            "deleteUser" => {
+                let username = params.required("username")?;
+                self.check_admin(user, None).await?;
+                backend.delete_user(user, username).await?;
+                None
+            }
+            "changePassword" => {
+                let username = params.required("username")?;
+                self.check_admin(user, Some(username)).await?;
+                let password = Secret::from(decode_password(params.required("password")?)?);
+                backend.change_password(user, username, &password).await?;
+                None
+            }
+
+            name => return not_implemented(name).await,
+        };
+        Ok(Reply::Info(Box::new(info)))
+    }
+
+    // the user management routes are for admins, or for the user themself when it's their own account
+    async fn check_admin(&self, user: &str, username: Option<&str>) -> Result<()> {
+        if username == Some(user) || self.backend.is_admin(user).await? {
+            return Ok(());
+        }
+        Err(Error::ServerError(
+            SubsonicErrorCode::NotAuthorized,
+            format!("{} is not a admin", user),
+        ))
+    }
+
+    // the type is versioned on its own, same as on the client side
+    fn album_list_query(&self, client: ApiVersion, params: &Params) -> Result<AlbumListQuery> {
+        let list_type = match params.required("type")? {
+            "random" => AlbumListType::Random,
+            "newest" => AlbumListType::Newest,
+            "highest" => AlbumListType::Highest,
+            "frequent" => AlbumListType::Frequent,
+            "recent" => AlbumListType::Recent,
+            "alphabeticalByName" => AlbumListType::AlphabeticalByName,
+            "alphabeticalByArtist" => AlbumListType::AlphabeticalByArtist,
+            "starred" => AlbumListType::Starred,
+            "byYear" => AlbumListType::ByYear {
+                from: params.required_parse("fromYear")?,
+                to: params.required_parse("toYear")?,
+            },
+            "byGenre" => AlbumListType::ByGenre {
+                genre: params.required("genre")?.to_string(),
+            },
+            x => {
+                return Err(Error::ServerError(
+                    SubsonicErrorCode::Generic,
+                    format!("bad value {:?} for type: not a album list type", x),
+                ))
+            }
+        };
+        self.check_since(client, &format!("type={}", list_type), list_type.since())?;
+        Ok(AlbumListQuery {
+            list_type,
+            size: params.parse("size")?.unwrap_or(10).min(500),
+            offset: params.parse("offset")?.unwrap_or(0),
+            music_folder_id: params.get("musicFolderId").map(|x| x.to_string()),
+        })
+    }
+}
+
+// WIDTHxHEIGHT, ex: 640x480
+fn video_size(text: &str) -> Result<VideoSize> {
+    let (width, height) = text.split_once('x').ok_or_else(|| {
+        Error::ServerError(
+            SubsonicErrorCode::Generic,
+            format!("bad value {:?} for size: expected WIDTHxHEIGHT", text),
+        )
+    })?;
+    Ok(VideoSize {
+        width: parse_value("size", width)?,
+        height: parse_value("size", height)?,
+    })
+}
+
+// a password as sent, either as is or hex encoded after enc:
+fn decode_password(sent: &str) -> Result<String> {
+    let hex = match sent.strip_prefix("enc:") {
+        Some(x) => x,
+        None => return Ok(sent.to_string()),
+    };
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|x| u8::from_str_radix(x, 16).ok()))
+        .collect::<Option<Vec<u8>>>();
+    bytes
+        .and_then(|x| String::from_utf8(x).ok())
+        .ok_or_else(|| {
+            Error::ServerError(
+                SubsonicErrorCode::WrongCredentials,
+                "the password is not valid hex".to_string(),
+            )
+        })
+}
+
+// doesn't stop at the first difference, so how long it takes doesn't say how much of a secret matched
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |accum, (x, y)| accum | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockServer;
+    use futures::executor::block_on;
+    use std::sync::{Arc, Mutex};
+
+    // two users (admin and bob), one album of two songs, and whatever gets starred
+    #[derive(Default)]
+    struct Library {
+        starred: Mutex<Vec<SongId>>,
+        scrobbled: Mutex<Vec<(SongId, Option<Timestamp>)>>,
+        // (username, new password), or None for a deleted user
+        users: Mutex<Vec<(String, Option<String>)>>,
+    }
+
+    fn song(id: &str) -> Media {
+        Media {
+            id: id.into(),
+            title: format!("song {}", id),
+            album_id: Some("10".into()),
+            ..Default::default()
+        }
+    }
+
+    impl SubsonicBackend for Library {
+        async fn password(&self, username: &str) -> Result<Option<Secret>> {
+            Ok(match username {
+                "admin" => Some(Secret::from("sesame")),
+                "bob" => Some(Secret::from("hunter2")),
+                _ => None,
+            })
+        }
+
+        // is_admin is left to its default, which asks this
+        async fn get_user(&self, _user: &str, username: &str) -> Result<User> {
+            Ok(User {
+                admin_role: username == "admin",
+                username: username.to_string(),
+                ..Default::default()
+            })
+        }
+
+        async fn api_key_user(&self, key: &str) -> Result<Option<String>> {
+            Ok((key == "key123").then(|| "admin".to_string()))
+        }
+
+        fn extensions(&self) -> Vec<Extension> {
+            vec![Extension::ApiKeyAuthentication]
+        }
+
+        async fn get_album(&self, _user: &str, id: &AlbumId) -> Result<Album> {
+            if id.as_str() != "10" {
+                return Err(Error::ServerError(SubsonicErrorCode::NotFound, "no such album".to_string()));
+            }
+            Ok(Album {
+                id: id.clone(),
+                title: "Arrival".to_string(),
+                songs: Some(vec![song("1"), song("2")]),
+                ..Default::default()
+            })
+        }
+
+        async fn get_album_list(&self, user: &str, _query: &AlbumListQuery) -> Result<Vec<Album>> {
+            Ok(vec![self.get_album(user, &AlbumId::from("10")).await?])
+        }
+
+        async fn search(&self, _user: &str, query: &SearchQuery) -> Result<SearchResult3> {
+            let songs = [song("1"), song("2")]
+                .into_iter()
+                .filter(|x| x.title.contains(&query.query))
+                .skip(query.song_offset as usize)
+                .take(query.song_count as usize)
+                .collect();
+            Ok(SearchResult3 {
+                songs,
+                ..Default::default()
+            })
+        }
+
+        async fn stream(&self, _user: &str, id: &SongId, options: &StreamOptions) -> Result<MediaStream> {
+            let body = format!("{} at {:?}", id, options.max_bit_rate);
+            Ok(MediaStream::from_bytes("audio/mpeg", body))
+        }
+
+        async fn star(&self, _user: &str, items: &StarItems) -> Result<()> {
+            self.starred.lock().unwrap().extend(items.ids.iter().cloned());
+            Ok(())
+        }
+
+        async fn get_starred(&self, _user: &str, _music_folder_id: Option<&str>) -> Result<Starred> {
+            let songs = self.starred.lock().unwrap().iter().map(|x| song(x.as_str())).collect();
+            Ok(Starred {
+                songs,
+                ..Default::default()
+            })
+        }
+
+        async fn scrobble(&self, _user: &str, id: &SongId, time: Option<Timestamp>, _submission: bool) -> Result<()> {
+            self.scrobbled.lock().unwrap().push((id.clone(), time));
+            Ok(())
+        }
+
+        async fn delete_user(&self, _user: &str, username: &str) -> Result<()> {
+            self.users.lock().unwrap().push((username.to_string(), None));
+            Ok(())
+        }
+
+        async fn change_password(&self, _user: &str, username: &str, password: &Secret) -> Result<()> {
+            let password = Some(password.expose().to_string());
+            self.users.lock().unwrap().push((username.to_string(), password));
+            Ok(())
+        }
+    }
+
+    // the server behind a real http socket, so the client can talk to it
+    fn serve(server: Arc<SubsonicServer<Library>>) -> MockServer {
+        MockServer::start(move |req| {
+            let resp = block_on(server.handle(&req.target, req.body.as_bytes()));
+            match resp.body {
+                Body::Text(text) if resp.content_type == JSON_TYPE => (JSON_TYPE, text),
+                Body::Text(text) => (XML_TYPE, text),
+                Body::Media(media) => (
+                    "audio/mpeg",
+                    String::from_utf8(block_on(media.bytes()).unwrap()).unwrap(),
+                ),
+            }
+        })
+    }
+
+    // a request straight to the handler, with the given params on top of the login
+    fn call(server: &SubsonicServer<Library>, endpoint: &str, params: &str) -> SubsonicResp {
+        let target = format!("/rest/{}?c=test&{}", endpoint, params);
+        match block_on(server.handle(&target, b"")).body {
+            Body::Text(text) => SubsonicResp::from_xml(&text).unwrap(),
+            Body::Media(_) => panic!("{} gave back media", endpoint),
+        }
+    }
+
+    fn code(resp: &SubsonicResp) -> Option<SubsonicErrorCode> {
+        resp.failure().map(|(code, _)| code)
+    }
+
+    #[test]
+    fn endpoint_table() {
+        let get_album = restapi::endpoint("getAlbum").unwrap();
+        assert_eq!(get_album.since, ApiVersion::new(1, 8, 0));
+        assert_eq!(get_album.arg_since("id"), Some(ApiVersion::new(1, 8, 0)));
+        assert_eq!(get_album.arg_since("nope"), None);
+
+        let stream = restapi::endpoint("stream").unwrap();
+        assert_eq!(stream.arg_since("maxBitRate"), Some(ApiVersion::new(1, 2, 0)));
+        assert_eq!(
+            restapi::endpoint("getLyricsBySongId").unwrap().extension,
+            Some(Extension::SongLyrics)
+        );
+        assert_eq!(
+            restapi::endpoint("getAlbumList2").unwrap().arg_since("musicFolderId"),
+            Some(ApiVersion::new(1, 11, 0))
+        );
+        assert!(restapi::endpoint("hls.m3u8").is_some());
+        assert!(restapi::endpoint("getNothing").is_none());
+    }
+
+    #[tokio::test]
+    async fn serves_the_client() {
+        let server = Arc::new(SubsonicServer::new(Library::default()));
+        let http = serve(server.clone());
+
+        let client = SubsonicClient::connect(&http.url, "admin", "sesame").await.unwrap();
+        assert_eq!(client.api_version(), LATEST_API_VERSION);
+        assert_eq!(client.server_info().kind.as_deref(), Some("subsonic_lib"));
+        assert!(client.supports(&Extension::FormPost));
+        assert!(client.supports(&Extension::ApiKeyAuthentication));
+
+        let album = client.get_album(&AlbumId::from("10")).await.unwrap();
+        assert_eq!(album.title, "Arrival");
+        assert_eq!(album.songs.unwrap().len(), 2);
+        assert!(matches!(
+            client.get_album(&AlbumId::from("11")).await,
+            Err(Error::ServerError(SubsonicErrorCode::NotFound, _))
+        ));
+
+        let found = client
+            .search3("song 2", Some(0), None, Some(0), None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(found.songs, [song("2")]);
+
+        client.star(&[SongId::from("2")], &[], &[]).await.unwrap();
+        assert_eq!(client.get_starred2(None).await.unwrap().songs, [song("2")]);
+
+        let time = Timestamp::from_millis(1_700_000_000_000);
+        client.scrobble(&SongId::from("1"), Some(time), None).await.unwrap();
+        assert_eq!(
+            *server.backend().scrobbled.lock().unwrap(),
+            [(SongId::from("1"), Some(time))]
+        );
+
+        let media = client
+            .stream(&SongId::from("1"), Some(320), None, None, None, None, None)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(media, b"1 at Some(320)");
+
+        // anything the backend leaves out is a error, not a hang or a 404
+        assert!(matches!(
+            client.get_genres().await,
+            Err(Error::ServerError(SubsonicErrorCode::Generic, message)) if message.contains("get_genres")
+        ));
+
+        // json and api keys go through the same way
+        let client = SubsonicClient::builder(&http.url, "", "")
+            .auth(Auth::ApiKey("key123".into()))
+            .format(ResponseFormat::Json)
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(client.get_album(&AlbumId::from("10")).await.unwrap().title, "Arrival");
+    }
+
+    #[test]
+    fn logins() {
+        let server = SubsonicServer::new(Library::default());
+        let v = "v=1.16.1";
+
+        let token = auth::token("sesame", "c19b2d");
+        assert_eq!(code(&call(&server, "ping", &format!("{}&u=admin&t={}&s=c19b2d", v, token))), None);
+        assert_eq!(code(&call(&server, "ping", &format!("{}&u=admin&p=sesame", v))), None);
+        assert_eq!(code(&call(&server, "ping", &format!("{}&u=admin&p=enc:736573616d65", v))), None);
+        assert_eq!(code(&call(&server, "ping", &format!("{}&apiKey=key123", v))), None);
+
+        // right for the salt, but the salt is too short to trust
+        let short_salt = format!("u=admin&t={}&s=c19b2", auth::token("sesame", "c19b2"));
+        let wrong = [
+            ("u=admin&p=open", SubsonicErrorCode::WrongCredentials),
+            ("u=nobody&p=sesame", SubsonicErrorCode::WrongCredentials),
+            ("u=admin&t=0123&s=c19b2d", SubsonicErrorCode::WrongCredentials),
+            (&short_salt, SubsonicErrorCode::WrongCredentials),
+            ("u=admin&p=enc:zz", SubsonicErrorCode::WrongCredentials),
+            ("u=admin", SubsonicErrorCode::MissingParameter),
+            ("p=sesame", SubsonicErrorCode::MissingParameter),
+            ("apiKey=nope", SubsonicErrorCode::InvalidApiKey),
+            ("apiKey=key123&u=admin", SubsonicErrorCode::ConflictingAuthMechanisms),
+        ];
+        for (login, expected) in wrong {
+            let resp = call(&server, "ping", &format!("{}&{}", v, login));
+            assert_eq!(resp.status, "failed");
+            assert_eq!(code(&resp), Some(expected), "{}", login);
+        }
+
+        // a failed response still says what version the server is, it's how clients find out
+        let resp = call(&server, "ping", "");
+        assert_eq!(code(&resp), Some(SubsonicErrorCode::MissingParameter));
+        assert_eq!(resp.version, "1.16.1");
+    }
+
+    #[test]
+    fn users() {
+        let server = SubsonicServer::new(Library::default());
+        let as_user = |login: &str, endpoint: &str, params: &str| {
+            code(&call(&server, endpoint, &format!("{}&v=1.16.1&{}", login, params)))
+        };
+        let at = |endpoint: &str, params: &str| as_user("u=admin&p=sesame", endpoint, params);
+
+        assert_eq!(at("changePassword", "username=bob&password=hunter2"), None);
+        assert_eq!(at("changePassword", "username=bob&password=enc:68756e74657233"), None);
+        assert_eq!(at("deleteUser", "username=bob"), None);
+        assert_eq!(
+            *server.backend().users.lock().unwrap(),
+            [
+                ("bob".to_string(), Some("hunter2".to_string())),
+                ("bob".to_string(), Some("hunter3".to_string())),
+                ("bob".to_string(), None),
+            ]
+        );
+
+        assert_eq!(at("changePassword", "username=bob"), Some(SubsonicErrorCode::MissingParameter));
+        assert_eq!(at("deleteUser", ""), Some(SubsonicErrorCode::MissingParameter));
+        // past the admin check, to a backend without get_users
+        assert_eq!(at("getUsers", ""), Some(SubsonicErrorCode::Generic));
+        assert_eq!(at("getUser", "username=bob"), None);
+
+        // everyone else only gets at their own account
+        let bob = |endpoint: &str, params: &str| as_user("u=bob&p=hunter2", endpoint, params);
+        assert_eq!(bob("getUser", "username=bob"), None);
+        assert_eq!(bob("changePassword", "username=bob&password=hunter4"), None);
+        let refused = [
+            ("getUser", "username=admin"),
+            ("getUsers", ""),
+            ("changePassword", "username=admin&password=mine"),
+            ("deleteUser", "username=admin"),
+            ("deleteUser", "username=bob"),
+        ];
+        for (endpoint, params) in refused {
+            assert_eq!(bob(endpoint, params), Some(SubsonicErrorCode::NotAuthorized), "{} {}", endpoint, params);
+        }
+        let users = server.backend().users.lock().unwrap();
+        assert_eq!(users.len(), 4);
+        assert_eq!(users[3], ("bob".to_string(), Some("hunter4".to_string())));
+    }
+
+    #[test]
+    fn versions() {
+        let server = SubsonicServer::new(Library::default()).version(ApiVersion::new(1, 12, 0));
+        let login = "u=admin&p=sesame";
+        let at = |endpoint: &str, v: &str, params: &str| {
+            code(&call(&server, endpoint, &format!("{}&v={}&{}", login, v, params)))
+        };
+
+        assert_eq!(at("getAlbum", "1.12.0", "id=10"), None);
+        // older clients are served anything the server has, even what's newer than they said
+        assert_eq!(at("getAlbum", "1.7.0", "id=10"), None);
+        // but newer endpoints than the server's version aren't
+        assert_eq!(at("getVideoInfo", "1.16.1", "id=1"), Some(SubsonicErrorCode::ServerTooOld));
+        assert_eq!(at("getVideoInfo", "1.1.0", "id=1"), Some(SubsonicErrorCode::ServerTooOld));
+        // and a different major version is a different protocol
+        assert_eq!(at("ping", "2.0.0", ""), Some(SubsonicErrorCode::ServerTooOld));
+        assert_eq!(at("ping", "0.9.0", ""), Some(SubsonicErrorCode::ClientTooOld));
+
+        // args are checked the same way
+        assert_eq!(at("search3", "1.11.0", "query=a&musicFolderId=1"), None);
+        assert_eq!(
+            at("getAlbumList2", "1.9.0", "type=byYear&fromYear=1970&toYear=1980"),
+            None
+        );
+        let old = SubsonicServer::new(Library::default()).version(ApiVersion::new(1, 10, 0));
+        let at = |endpoint: &str, params: &str| {
+            code(&call(&old, endpoint, &format!("{}&v=1.16.1&{}", login, params)))
+        };
+        assert_eq!(at("search3", "query=a"), None);
+        assert_eq!(at("getAlbumList2", "type=newest"), None);
+        assert_eq!(at("search3", "query=a&musicFolderId=1"), Some(SubsonicErrorCode::ServerTooOld));
+        assert_eq!(
+            at("getAlbumList2", "type=byGenre&genre=Rock"),
+            Some(SubsonicErrorCode::ServerTooOld)
+        );
+
+        // only the known endpoints under /rest/ are answered, the rest of /rest/ is a subsonic error
+        let resp = call(&server, "getNothing", login);
+        assert_eq!(code(&resp), Some(SubsonicErrorCode::NotFound));
+        assert_eq!(block_on(server.handle("/index.html", b"")).status, 404);
+    }
+
+    #[test]
+    fn form_posts_and_formats() {
+        let server = SubsonicServer::new(Library::default());
+        let resp = block_on(server.handle(
+            "/rest/getAlbum.view",
+            b"u=admin&p=sesame&v=1.16.1&c=test&f=json&id=10",
+        ));
+        assert_eq!(resp.content_type, JSON_TYPE);
+        let album = match resp.body {
+            Body::Text(text) => SubsonicResp::from_json(&text).unwrap(),
+            Body::Media(_) => panic!("an album is not media"),
+        };
+        assert!(matches!(album.resp, Some(SubsonicInfo::Album(x)) if x.title == "Arrival"));
+    }
+}
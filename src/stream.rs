@@ -0,0 +1,308 @@
+use super::*;
+use bytes::Bytes;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use reqwest::header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// the most bytes() sets aside before the body comes in, past that it grows as it reads
+const MAX_PREALLOCATE: u64 = 16 << 20;
+
+// the size param for stream, it's sent as WIDTHxHEIGHT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::fmt::Display for VideoSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+// the body of a binary endpoint (stream, download, get_cover_art, get_avatar)
+// along with what the headers said about it
+// this is a Stream of the raw bytes, so it can be fed to a decoder or file as it comes in
+pub struct MediaStream {
+    pub content_type: Option<String>,
+    pub content_length: Option<u64>,
+    pub suffix: Option<String>,
+    body: BoxStream<'static, Result<Bytes>>,
+}
+
+impl std::fmt::Debug for MediaStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MediaStream")
+            .field("content_type", &self.content_type)
+            .field("content_length", &self.content_length)
+            .field("suffix", &self.suffix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for MediaStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().body.poll_next_unpin(cx)
+    }
+}
+
+impl MediaStream {
+    // a body from anywhere else, ex: a file a server is sending
+    pub fn new(
+        content_type: Option<String>,
+        content_length: Option<u64>,
+        body: impl Stream<Item = Result<Bytes>> + Send + 'static,
+    ) -> Self {
+        let suffix = content_type.as_deref().and_then(mime_suffix);
+        MediaStream {
+            content_type,
+            content_length,
+            suffix,
+            body: body.boxed(),
+        }
+    }
+
+    // a body that's already all in memory
+    pub fn from_bytes(content_type: &str, bytes: impl Into<Bytes>) -> Self {
+        let bytes = bytes.into();
+        MediaStream::new(
+            Some(content_type.to_string()),
+            Some(bytes.len() as u64),
+            futures::stream::once(async { Ok(bytes) }),
+        )
+    }
+
+    fn from_headers(headers: &HeaderMap, body: BoxStream<'static, Result<Bytes>>) -> Self {
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.to_string());
+        let content_length = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.parse::<u64>().ok());
+        // the filename is the most reliable place for the suffix,
+        // but not every server sends one so fall back on the mime type
+        let suffix = headers
+            .get(CONTENT_DISPOSITION)
+            .and_then(|x| x.to_str().ok())
+            .and_then(disposition_suffix)
+            .or_else(|| content_type.as_deref().and_then(mime_suffix));
+
+        MediaStream {
+            content_type,
+            content_length,
+            suffix,
+            body,
+        }
+    }
+
+    // read the whole body into memory
+    pub async fn bytes(mut self) -> Result<Vec<u8>> {
+        // the length is only the server's word for it, so a huge one isn't allocated up front
+        let capacity = self.content_length.unwrap_or(0).min(MAX_PREALLOCATE);
+        let mut out = Vec::with_capacity(capacity as usize);
+        while let Some(chunk) = self.next().await {
+            out.extend_from_slice(&chunk?);
+        }
+        Ok(out)
+    }
+}
+
+// pulls the extension off of a content disposition filename
+// ex: attachment; filename="01 - Intro.flac" -> flac
+fn disposition_suffix(disposition: &str) -> Option<String> {
+    let filename = disposition
+        .split(';')
+        .map(|x| x.trim())
+        .find_map(|x| x.strip_prefix("filename="))?
+        .trim_matches('"');
+    let (_, ext) = filename.rsplit_once('.')?;
+    (!ext.is_empty()).then(|| ext.to_ascii_lowercase())
+}
+
+// a best guess for the suffix of the common media types
+fn mime_suffix(mime: &str) -> Option<String> {
+    let mime = mime.split(';').next()?.trim().to_ascii_lowercase();
+    Some(
+        match mime.as_str() {
+            "audio/mpeg" => "mp3",
+            "audio/flac" | "audio/x-flac" => "flac",
+            "audio/ogg" => "ogg",
+            "audio/mp4" | "audio/x-m4a" => "m4a",
+            "audio/aac" => "aac",
+            "audio/wav" | "audio/x-wav" => "wav",
+            "audio/opus" => "opus",
+            "video/mp4" => "mp4",
+            "video/x-flv" => "flv",
+            "video/webm" => "webm",
+            "image/jpeg" => "jpg",
+            "image/png" => "png",
+            "image/gif" => "gif",
+            _ => mime.split_once('/')?.1,
+        }
+        .to_string(),
+    )
+}
+
+// the server sends errors as a normal subsonic response,
+// so a xml or json body is not the data we asked for
+// only the exact types count, media can be xml too (ex: image/svg+xml cover art)
+fn response_format(headers: &HeaderMap) -> Option<ResponseFormat> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    match mime.as_str() {
+        "text/xml" | "application/xml" => Some(ResponseFormat::Xml),
+        "application/json" => Some(ResponseFormat::Json),
+        _ => None,
+    }
+}
+
+impl SubsonicClient {
+    pub(crate) async fn make_stream_req(
+        &self,
+        endpoint: &'static str,
+        url: Url,
+    ) -> Result<MediaStream> {
+        let resp = self.make_raw_req(endpoint, url).await?;
+        let headers = resp.headers().clone();
+        Ok(MediaStream::from_headers(
+            &headers,
+            resp.bytes_stream()
+                .map(move |x| x.map_err(|e| Error::http(endpoint, e)))
+                .boxed(),
+        ))
+    }
+
+    // a request for anything that isn't a subsonic response (media, images, playlists)
+    // a xml or json body here means the server is telling us something went wrong
+    pub(crate) async fn make_raw_req(
+        &self,
+        endpoint: &'static str,
+        url: Url,
+    ) -> Result<reqwest::Response> {
+        let resp = async { self.request(url).send().await?.error_for_status() }
+            .await
+            .map_err(|e| Error::http(endpoint, e))?;
+        if let Some(format) = response_format(resp.headers()) {
+            let body = resp.text().await.map_err(|e| Error::http(endpoint, e))?;
+            let resp = format.decode(endpoint, &body)?;
+            return Err(match resp.failure() {
+                Some((code, message)) => Error::ServerError(code, message),
+                None => Error::UnexpectedResponse {
+                    endpoint,
+                    expected: "binary data",
+                },
+            });
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::deserialize::SubsonicErrorCode;
+    use crate::mock::{xml, MockServer};
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &'static str)]) -> HeaderMap {
+        let mut out = HeaderMap::new();
+        for (k, v) in pairs {
+            out.insert(k, HeaderValue::from_static(v));
+        }
+        out
+    }
+
+    #[test]
+    fn headers_are_read() {
+        let stream = MediaStream::from_headers(
+            &headers(&[
+                (CONTENT_TYPE, "audio/mpeg"),
+                (CONTENT_LENGTH, "5000000000"),
+                (
+                    CONTENT_DISPOSITION,
+                    "attachment; filename=\"01 - Intro.FLAC\"",
+                ),
+            ]),
+            futures::stream::empty().boxed(),
+        );
+        assert_eq!(stream.content_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(stream.content_length, Some(5_000_000_000));
+        assert_eq!(stream.suffix.as_deref(), Some("flac"));
+    }
+
+    #[test]
+    fn suffix_falls_back_on_mime() {
+        let stream = MediaStream::from_headers(
+            &headers(&[(CONTENT_TYPE, "image/jpeg; charset=binary")]),
+            futures::stream::empty().boxed(),
+        );
+        assert_eq!(stream.content_length, None);
+        assert_eq!(stream.suffix.as_deref(), Some("jpg"));
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_media() {
+        let server = MockServer::start(|req| {
+            if req.query("id").as_deref() == Some("1") {
+                ("audio/mpeg", "ID3 and then some".to_string())
+            } else {
+                xml("failed", "1.16.1", r#"<error code="70" message="not found"/>"#)
+            }
+        });
+        let client = SubsonicClient::builder(&server.url, "admin", "sesame")
+            .build()
+            .unwrap();
+
+        let song = client
+            .stream(&SongId::from("1"), None, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(song.bytes().await.unwrap(), b"ID3 and then some");
+
+        let missing = SongId::from("2");
+        let streamed = client.stream(&missing, None, None, None, None, None, None).await;
+        assert!(
+            matches!(streamed, Err(Error::ServerError(SubsonicErrorCode::NotFound, _))),
+            "{:?}",
+            streamed.map(|x| x.content_type)
+        );
+        let downloaded = client.download(&missing).await;
+        assert!(
+            matches!(downloaded, Err(Error::ServerError(SubsonicErrorCode::NotFound, _))),
+            "{:?}",
+            downloaded.map(|x| x.content_type)
+        );
+    }
+
+    #[tokio::test]
+    async fn huge_lengths_are_not_allocated() {
+        let stream = MediaStream::from_headers(
+            &headers(&[(CONTENT_LENGTH, "18446744073709551615")]),
+            futures::stream::iter([Ok(Bytes::from_static(b"short"))]).boxed(),
+        );
+        assert_eq!(stream.content_length, Some(u64::MAX));
+        assert_eq!(stream.bytes().await.unwrap(), b"short");
+    }
+
+    #[test]
+    fn responses_are_detected() {
+        let format = |x| response_format(&headers(&[(CONTENT_TYPE, x)]));
+        assert_eq!(format("text/xml; charset=utf-8"), Some(ResponseFormat::Xml));
+        assert_eq!(format("application/xml"), Some(ResponseFormat::Xml));
+        assert_eq!(
+            format("application/json; charset=utf-8"),
+            Some(ResponseFormat::Json)
+        );
+        assert_eq!(format("Text/XML"), Some(ResponseFormat::Xml));
+        assert_eq!(format("audio/ogg"), None);
+        assert_eq!(format("image/svg+xml"), None);
+        assert_eq!(format("application/xhtml+xml"), None);
+        assert_eq!(format("application/ld+json"), None);
+        assert_eq!(response_format(&HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,304 @@
+use serde::de::Visitor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use time::format_description::well_known::{Iso8601, Rfc3339};
+use time::{Date, OffsetDateTime, PrimitiveDateTime};
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+// a point in time, as the api sends it
+// responses carry iso 8601 strings (ex: 2007-03-15T06:36:55 or 2023-03-27T09:45:27.5Z) and
+// args are milliseconds since the epoch, both are read into this
+// it converts to and from time's datetimes, and chrono's with the chrono feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(SystemTime);
+
+impl Timestamp {
+    pub fn now() -> Self {
+        Timestamp(SystemTime::now())
+    }
+
+    pub fn from_millis(millis: i64) -> Self {
+        Timestamp::from_unix(
+            millis.div_euclid(1000),
+            millis.rem_euclid(1000) as u32 * 1_000_000,
+        )
+    }
+
+    // negative before 1970, times past what a i64 of milliseconds holds are clamped to it
+    pub fn as_millis(&self) -> i64 {
+        let (secs, nanos) = self.unix();
+        // from_millis(i64::MIN) rounds the seconds down past i64::MIN / 1000, so it's worked out wider
+        let millis = secs as i128 * 1000 + (nanos / 1_000_000) as i128;
+        millis.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+
+    pub fn system_time(&self) -> SystemTime {
+        self.0
+    }
+
+    fn from_unix(secs: i64, nanos: u32) -> Self {
+        let time = if secs >= 0 {
+            UNIX_EPOCH + Duration::new(secs as u64, nanos)
+        } else {
+            UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()) + Duration::from_nanos(nanos as u64)
+        };
+        Timestamp(time)
+    }
+
+    // (whole seconds since the epoch, nanoseconds past that), the nanoseconds are never negative
+    fn unix(&self) -> (i64, u32) {
+        match self.0.duration_since(UNIX_EPOCH) {
+            Ok(x) => (x.as_secs() as i64, x.subsec_nanos()),
+            Err(e) => {
+                let x = e.duration();
+                let secs = -(x.as_secs() as i64);
+                match x.subsec_nanos() {
+                    0 => (secs, 0),
+                    nanos => (secs - 1, NANOS_PER_SEC - nanos),
+                }
+            }
+        }
+    }
+}
+
+impl Default for Timestamp {
+    fn default() -> Self {
+        Timestamp(UNIX_EPOCH)
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Timestamp(time)
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(time: Timestamp) -> Self {
+        time.0
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Timestamp(time.into())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+    fn from(time: Timestamp) -> Self {
+        time.0.into()
+    }
+}
+
+impl From<OffsetDateTime> for Timestamp {
+    fn from(time: OffsetDateTime) -> Self {
+        Timestamp(time.into())
+    }
+}
+
+impl From<Timestamp> for OffsetDateTime {
+    fn from(time: Timestamp) -> Self {
+        time.0.into()
+    }
+}
+
+// always utc, ex: 2007-03-15T06:36:55Z
+// the fraction is only written when there is one, times past year 9999 fall back to milliseconds
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (secs, nanos) = self.unix();
+        let iso = OffsetDateTime::from_unix_timestamp(secs)
+            .ok()
+            .and_then(|x| x.replace_nanosecond(nanos).ok())
+            .and_then(|x| x.format(&Rfc3339).ok());
+        match iso {
+            Some(x) => f.write_str(&x),
+            None => write!(f, "{}", self.as_millis()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTimestampError(String);
+
+impl std::fmt::Display for ParseTimestampError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} is not a iso 8601 date and time", self.0)
+    }
+}
+
+impl std::error::Error for ParseTimestampError {}
+
+impl FromStr for Timestamp {
+    type Err = ParseTimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s.trim()).ok_or_else(|| ParseTimestampError(s.to_string()))
+    }
+}
+
+// a iso 8601 date, optionally with a time and a zone
+// no zone is read as utc, which is what subsonic means by it
+fn parse(s: &str) -> Option<Timestamp> {
+    // some servers put a space where the T goes
+    let s = match s.split_at_checked(10) {
+        Some((date, time)) if time.starts_with(' ') => format!("{}T{}", date, &time[1..]),
+        _ => s.to_string(),
+    };
+    let time = OffsetDateTime::parse(&s, &Iso8601::PARSING)
+        .or_else(|_| PrimitiveDateTime::parse(&s, &Iso8601::PARSING).map(|x| x.assume_utc()))
+        .or_else(|_| Date::parse(&s, &Iso8601::PARSING).map(|x| x.midnight().assume_utc()))
+        .ok()?;
+    Some(Timestamp::from_unix(time.unix_timestamp(), time.nanosecond()))
+}
+
+// written as a iso string, which reads back the same
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// an iso string, or milliseconds (ex: chatMessage time) as a number or a string of digits
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimestampVisitor;
+
+        impl<'de> Visitor<'de> for TimestampVisitor {
+            type Value = Timestamp;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a iso 8601 date or milliseconds since the epoch")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                match v.parse::<i64>() {
+                    Ok(millis) => Ok(Timestamp::from_millis(millis)),
+                    Err(_) => v.parse().map_err(E::custom),
+                }
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                i64::try_from(v)
+                    .map(Timestamp::from_millis)
+                    .map_err(|_| E::custom(format!("{} milliseconds is out of range", v)))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(Timestamp::from_millis(v))
+            }
+        }
+
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECS_PER_DAY: i64 = 86_400;
+
+    fn parsed(s: &str) -> Option<i64> {
+        s.parse::<Timestamp>().ok().map(|x| x.as_millis())
+    }
+
+    #[test]
+    fn parsing() {
+        // (input, milliseconds since the epoch)
+        let table = [
+            ("1970-01-01T00:00:00", Some(0)),
+            ("2007-03-15T06:36:55", Some(1_173_940_615_000)),
+            ("2007-03-15T06:36:55Z", Some(1_173_940_615_000)),
+            ("2007-03-15 06:36:55", Some(1_173_940_615_000)),
+            ("2007-03-15T08:36:55+02:00", Some(1_173_940_615_000)),
+            ("2007-03-15T01:06:55-0530", Some(1_173_940_615_000)),
+            ("2007-03-15T07:36:55+01", Some(1_173_940_615_000)),
+            ("2007-03-15T06:36", Some(1_173_940_560_000)),
+            ("2023-03-14T17:51:22.112827504Z", Some(1_678_816_282_112)),
+            ("2023-03-14T17:51:22.5", Some(1_678_816_282_500)),
+            ("2011-02-03", Some(1_296_691_200_000)),
+            ("2000-02-29T00:00:00Z", Some(951_782_400_000)),
+            ("1969-12-31T23:59:59.9Z", Some(-100)),
+            ("1900-01-01T00:00:00Z", Some(-2_208_988_800_000)),
+            ("2001-02-29T00:00:00Z", None),
+            ("2007-13-15T06:36:55", None),
+            ("2007-03-15T24:00:00", None),
+            ("2007-03-15T06:36:55.", None),
+            ("2007-03-15T06:36:55Zjunk", None),
+            ("2007-3-15", None),
+            ("", None),
+            ("yesterday", None),
+        ];
+        for (s, millis) in table {
+            assert_eq!(parsed(s), millis, "{}", s);
+        }
+    }
+
+    #[test]
+    fn displaying() {
+        for (s, shown) in [
+            ("2007-03-15T06:36:55", "2007-03-15T06:36:55Z"),
+            ("2023-03-14T19:51:22.1128275+02:00", "2023-03-14T17:51:22.1128275Z"),
+            ("1969-12-31T23:59:59.9Z", "1969-12-31T23:59:59.9Z"),
+            ("2000-02-29T12:00:00Z", "2000-02-29T12:00:00Z"),
+        ] {
+            let time: Timestamp = s.parse().unwrap();
+            assert_eq!(time.to_string(), shown);
+            assert_eq!(shown.parse::<Timestamp>().unwrap(), time);
+        }
+        // every day for a few centuries comes back the same
+        for days in (-200 * 365..200 * 365).step_by(13) {
+            let time = Timestamp::from_unix(days * SECS_PER_DAY + 45_296, 0);
+            assert_eq!(time.to_string().parse::<Timestamp>().unwrap(), time);
+        }
+    }
+
+    #[test]
+    fn deserializing() {
+        // json numbers and digit strings are milliseconds
+        let times: Vec<Timestamp> =
+            serde_json::from_str(r#"["2007-03-15T06:36:55", 1173940615000, "1173940615000"]"#)
+                .unwrap();
+        assert!(times.iter().all(|x| *x == times[0]));
+        assert!(serde_json::from_str::<Timestamp>(r#""not a date""#).is_err());
+        // past i64::MAX, not wrapped around to before 1970
+        assert!(serde_json::from_str::<Timestamp>("9223372036854775808").is_err());
+        // the ends of the range are fine, and written back out as milliseconds since they're no iso year
+        for millis in [i64::MIN, i64::MAX] {
+            let time: Timestamp = serde_json::from_str(&millis.to_string()).unwrap();
+            assert_eq!(time.as_millis(), millis);
+            assert_eq!(time.to_string(), millis.to_string());
+            let json = serde_json::to_string(&time).unwrap();
+            assert_eq!(serde_json::from_str::<Timestamp>(&json).unwrap(), time);
+        }
+
+        let time = Timestamp::from(UNIX_EPOCH + Duration::from_millis(1_173_940_615_000));
+        assert_eq!(SystemTime::from(time), time.system_time());
+        assert_eq!(time, times[0]);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        let time: Timestamp = "2023-03-14T17:51:22.5Z".parse().unwrap();
+        let chrono: chrono::DateTime<chrono::Utc> = time.into();
+        assert_eq!(chrono.to_rfc3339(), "2023-03-14T17:51:22.500+00:00");
+        assert_eq!(Timestamp::from(chrono), time);
+    }
+
+    #[test]
+    fn time() {
+        let time: Timestamp = "2023-03-14T17:51:22.5Z".parse().unwrap();
+        let offset: OffsetDateTime = time.into();
+        assert_eq!(offset.unix_timestamp(), 1_678_816_282);
+        assert_eq!(offset.millisecond(), 500);
+        assert_eq!(Timestamp::from(offset), time);
+    }
+}
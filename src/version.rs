@@ -0,0 +1,107 @@
+use std::str::FromStr;
+
+// a subsonic api version, ex: 1.16.1
+// ordering is lexicographic over (major, minor, patch), so 2.0.0 > 1.16.1 > 1.15.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ApiVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ApiVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        ApiVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseVersionError(String);
+
+impl std::fmt::Display for ParseVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} is not a api version (major.minor or major.minor.patch)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseVersionError {}
+
+// some servers leave off the patch, so 1.16 is read as 1.16.0
+impl FromStr for ApiVersion {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseVersionError(s.to_string());
+        let parts = s
+            .trim()
+            .split('.')
+            .map(|x| x.parse::<u32>().map_err(|_| err()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match parts[..] {
+            [major, minor] => Ok(ApiVersion::new(major, minor, 0)),
+            [major, minor, patch] => Ok(ApiVersion::new(major, minor, patch)),
+            _ => Err(err()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ordering() {
+        // (server, required, allowed)
+        let table = [
+            ((1, 16, 1), (1, 16, 1), true),
+            ((1, 16, 0), (1, 15, 1), true),
+            ((2, 0, 0), (1, 13, 0), true),
+            ((2, 0, 0), (1, 16, 1), true),
+            ((1, 13, 0), (1, 13, 0), true),
+            ((1, 13, 5), (1, 13, 1), true),
+            ((1, 10, 2), (1, 9, 0), true),
+            ((1, 9, 0), (1, 10, 0), false),
+            ((1, 15, 0), (1, 15, 1), false),
+            ((1, 16, 1), (2, 0, 0), false),
+            ((0, 99, 99), (1, 0, 0), false),
+            ((1, 2, 0), (1, 10, 1), false),
+        ];
+        for (server, required, allowed) in table {
+            let server = ApiVersion::new(server.0, server.1, server.2);
+            let required = ApiVersion::new(required.0, required.1, required.2);
+            assert_eq!(
+                server >= required,
+                allowed,
+                "server {} for a {} api",
+                server,
+                required
+            );
+        }
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!("1.16.1".parse(), Ok(ApiVersion::new(1, 16, 1)));
+        assert_eq!("1.16".parse(), Ok(ApiVersion::new(1, 16, 0)));
+        assert_eq!(" 2.0.0 ".parse(), Ok(ApiVersion::new(2, 0, 0)));
+        assert!("1".parse::<ApiVersion>().is_err());
+        assert!("1.2.3.4".parse::<ApiVersion>().is_err());
+        assert!("1.x.0".parse::<ApiVersion>().is_err());
+        assert!("".parse::<ApiVersion>().is_err());
+        assert_eq!(ApiVersion::new(1, 8, 0).to_string(), "1.8.0");
+    }
+}